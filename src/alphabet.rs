@@ -0,0 +1,88 @@
+use std::{fmt::Debug, hash::Hash};
+
+/// The symbols a machine enciphers, in the order of the contacts of its rotors
+///
+/// `Character`, `Position` and `Cipher` are generic over the alphabet, so the same rotors, reflectors and stepping
+/// work for machines with any number of contacts
+///
+/// Other alphabets are added by implementing this trait on a unit struct. `SYMBOLS` must not repeat a symbol and
+/// must hold between 1 and 256 of them, other sizes fail to compile where the alphabet's characters are made.
+/// Rotors and reflectors for the alphabet are built with `CustomRotor::for_alphabet` and `Reflector::from_wiring`,
+/// and `RotorConfig::from_rotors` takes any number of them
+pub trait Alphabet:
+    Copy + Clone + Debug + Default + PartialEq + Eq + Hash + Send + Sync + 'static
+{
+    /// Symbols in the order of the contacts
+    const SYMBOLS: &'static [char];
+    /// Number of contacts
+    const SIZE: usize = Self::SYMBOLS.len();
+    /// Describes the valid symbols in error messages
+    const NAME: &'static str;
+
+    /// Index of the contact for `c`, if `c` is part of the alphabet
+    fn index(c: char) -> Option<u8> {
+        Self::SYMBOLS.iter().position(|s| *s == c).map(|i| i as u8)
+    }
+}
+
+/// The 26 letters `A` to `Z` of the Enigma. Lowercase letters are read as uppercase
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Latin;
+
+impl Alphabet for Latin {
+    const SYMBOLS: &'static [char] = &[
+        'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R',
+        'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+    ];
+    const NAME: &'static str = "A-Z";
+
+    fn index(c: char) -> Option<u8> {
+        match c.to_ascii_uppercase() {
+            upper @ 'A'..='Z' => Some(upper as u8 - b'A'),
+            _ => None,
+        }
+    }
+}
+
+/// The 10 digits `0` to `9` of the Enigma Z
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Digits;
+
+impl Alphabet for Digits {
+    const SYMBOLS: &'static [char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+    const NAME: &'static str = "0-9";
+
+    fn index(c: char) -> Option<u8> {
+        c.to_digit(10).map(|d| d as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alphabet, Digits, Latin};
+
+    #[test]
+    fn index() {
+        Latin::SYMBOLS
+            .iter()
+            .enumerate()
+            .for_each(|(i, c)| assert_eq!(Latin::index(*c), Some(i as u8)));
+        Digits::SYMBOLS
+            .iter()
+            .enumerate()
+            .for_each(|(i, c)| assert_eq!(Digits::index(*c), Some(i as u8)));
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(Latin::SIZE, 26);
+        assert_eq!(Digits::SIZE, 10);
+    }
+
+    #[test]
+    fn outside() {
+        assert_eq!(Latin::index('1'), None);
+        assert_eq!(Latin::index('b'), Some(1));
+        assert_eq!(Digits::index('A'), None);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::common::Character;
+use crate::{
+    alphabet::{Alphabet, Latin},
+    common::Character,
+};
 use anyhow::Context;
 use bruh_moment::{bruh, Bruh};
 use itertools::Itertools;
@@ -6,23 +9,22 @@ use nohash_hasher::BuildNoHashHasher;
 use std::{collections::HashMap, str::FromStr};
 use thiserror::Error;
 
-pub trait Encode {
-    fn encode(&self, c: Character) -> Character;
+pub trait Encode<A: Alphabet = Latin> {
+    fn encode(&self, c: Character<A>) -> Character<A>;
 }
 
-pub trait Decode {
-    fn decode(&self, c: Character) -> Character;
+pub trait Decode<A: Alphabet = Latin> {
+    fn decode(&self, c: Character<A>) -> Character<A>;
 }
 
-pub struct Cipher(
-    HashMap<Character, Character, BuildNoHashHasher<Character>>,
-    HashMap<Character, Character, BuildNoHashHasher<Character>>,
-);
+type CharacterMap<A> = HashMap<Character<A>, Character<A>, BuildNoHashHasher<Character<A>>>;
 
-impl Cipher {
-    fn new() -> Cipher {
-        let builder_l: BuildNoHashHasher<Character> = BuildNoHashHasher::default();
-        let builder_r: BuildNoHashHasher<Character> = BuildNoHashHasher::default();
+pub struct Cipher<A: Alphabet = Latin>(CharacterMap<A>, CharacterMap<A>);
+
+impl<A: Alphabet> Cipher<A> {
+    fn new() -> Cipher<A> {
+        let builder_l: BuildNoHashHasher<Character<A>> = BuildNoHashHasher::default();
+        let builder_r: BuildNoHashHasher<Character<A>> = BuildNoHashHasher::default();
         let hash_l = HashMap::with_hasher(builder_l);
         let hash_r = HashMap::with_hasher(builder_r);
         Cipher(hash_l, hash_r)
@@ -31,79 +33,76 @@ impl Cipher {
 
 #[derive(Error, Debug)]
 enum CipherError {
-    #[error("Cipher does not contain enough unique characters(26) is a character duplicated in the cipher? Recieved")]
-    Unique,
-    #[error("Recieved {0}: Cipher may only contain 26 characters")]
-    TooMany(usize),
-    #[error("Recieved {0}: Cipher may only contain 26 characters")]
-    TooFew(usize),
+    #[error("Cipher does not contain enough unique characters({0}) is a character duplicated in the cipher? Recieved")]
+    Unique(usize),
+    #[error("Recieved {0}: Cipher may only contain {1} characters")]
+    TooMany(usize, usize),
+    #[error("Recieved {0}: Cipher may only contain {1} characters")]
+    TooFew(usize, usize),
 }
 
-impl FromStr for Cipher {
+impl<A: Alphabet> FromStr for Cipher<A> {
     type Err = Bruh;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let res: Vec<Character> = s
+        let res: Vec<Character<A>> = s
             .chars()
-            .into_iter()
-            .map(|c| Character::try_from(c))
+            .map(Character::try_from)
             .try_collect()
-            .with_context(|| format!("Tried to create a cipher from a string"))?;
+            .context("Tried to create a cipher from a string")?;
 
-        match s.len() {
-            0..=25 => Err(bruh!(CipherError::TooFew(s.len()))),
-            26 => Cipher::try_from(res),
-            _ => Err(bruh!(CipherError::TooMany(s.len()))),
+        let len = res.len();
+        match len.cmp(&A::SIZE) {
+            std::cmp::Ordering::Less => Err(bruh!(CipherError::TooFew(len, A::SIZE))),
+            std::cmp::Ordering::Equal => Cipher::try_from(res),
+            std::cmp::Ordering::Greater => Err(bruh!(CipherError::TooMany(len, A::SIZE))),
         }
     }
 }
 
-impl TryFrom<Vec<Character>> for Cipher {
+impl<A: Alphabet> TryFrom<Vec<Character<A>>> for Cipher<A> {
     type Error = Bruh;
 
-    fn try_from(value: Vec<Character>) -> Result<Self, Self::Error> {
+    fn try_from(value: Vec<Character<A>>) -> Result<Self, Self::Error> {
         let res = value.iter().unique().count();
-        match res {
-            26 => ('A'..='Z')
-                .into_iter()
-                .map(|c| Character::try_from(c).unwrap())
-                .enumerate()
-                .fold(Ok(Cipher::new()), |acc, (i, next)| match acc {
-                    Ok(mut cipher) => {
-                        cipher.0.insert(next, value[i]);
-                        cipher.1.insert(value[i], next);
-                        Ok(cipher)
-                    }
-                    Err(_) => unreachable!(),
-                }),
-            _ => Err(bruh!(CipherError::Unique)),
+        match res == A::SIZE && value.len() == A::SIZE {
+            true => Ok(Character::<A>::all().enumerate().fold(
+                Cipher::new(),
+                |mut cipher, (i, next)| {
+                    cipher.0.insert(next, value[i]);
+                    cipher.1.insert(value[i], next);
+                    cipher
+                },
+            )),
+            false => Err(bruh!(CipherError::Unique(A::SIZE))),
         }
     }
 }
 
-impl Encode for Cipher {
-    fn encode(&self, c: Character) -> Character {
+impl<A: Alphabet> Encode<A> for Cipher<A> {
+    fn encode(&self, c: Character<A>) -> Character<A> {
         *self.0.get(&c).unwrap()
     }
 }
 
-impl Decode for Cipher {
-    fn decode(&self, c: Character) -> Character {
+impl<A: Alphabet> Decode<A> for Cipher<A> {
+    fn decode(&self, c: Character<A>) -> Character<A> {
         *self.1.get(&c).unwrap()
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_conversion, clippy::single_match)]
 mod tests_cipher {
     use std::str::FromStr;
 
-    use crate::common::Character;
+    use crate::{alphabet::Latin, common::Character};
 
     use super::{Cipher, Decode, Encode};
 
     #[test]
     fn codec() {
-        let cipher = Cipher::from_str("EKMFLGDQVZNTOWYHXUSPAIBRCJ").unwrap();
+        let cipher = Cipher::<Latin>::from_str("EKMFLGDQVZNTOWYHXUSPAIBRCJ").unwrap();
         ('A'..='Z')
             .into_iter()
             .map(|c| Character::try_from(c).unwrap())
@@ -160,7 +159,7 @@ mod tests_cipher {
 
     #[test]
     fn length_too_small() {
-        match Cipher::from_str("AZ") {
+        match Cipher::<Latin>::from_str("AZ") {
             Ok(_) => {
                 panic!("Should not be able to construct cipher less than 26 chacaters in length")
             }
@@ -170,7 +169,7 @@ mod tests_cipher {
 
     #[test]
     fn length_too_big() {
-        match Cipher::from_str("ZYXWVUTSRQPONMLKJIHGFEDCBAA") {
+        match Cipher::<Latin>::from_str("ZYXWVUTSRQPONMLKJIHGFEDCBAA") {
             Ok(_) => {
                 panic!("Should not be able to construct cipher greater than 26 chacaters in length")
             }
@@ -180,7 +179,7 @@ mod tests_cipher {
 
     #[test]
     fn no_duplicates() {
-        match Cipher::from_str("AAAAAAAAAAAAAAAAAAAAAAAAAA") {
+        match Cipher::<Latin>::from_str("AAAAAAAAAAAAAAAAAAAAAAAAAA") {
             Ok(_) => panic!("Cipher should contain unique characters"),
             Err(_) => (),
         }
@@ -188,7 +187,7 @@ mod tests_cipher {
 
     #[test]
     fn only_alphabetics() {
-        match Cipher::from_str("1234567890*+-;'!@#$%^&*()_") {
+        match Cipher::<Latin>::from_str("1234567890*+-;'!@#$%^&*()_") {
             Ok(_) => {
                 panic!("Should not be able to construct cipher with non-alphabetic characters")
             }
@@ -1,143 +1,168 @@
 use std::{
     fmt::{Display, Formatter},
+    marker::PhantomData,
     ops::{Add, Sub},
 };
 
 use nohash_hasher::IsEnabled;
 use thiserror::Error;
 
+use crate::alphabet::{Alphabet, Latin};
+
+/// A symbol of the alphabet `A`, stored as the index of its contact
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub struct Character(char);
+pub struct Character<A: Alphabet = Latin>(u8, PhantomData<A>);
 
-impl IsEnabled for Character {}
+impl<A: Alphabet> IsEnabled for Character<A> {}
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, PartialOrd)]
-pub struct Position(u8);
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, PartialOrd, Default)]
+pub struct Position<A: Alphabet = Latin>(u8, PhantomData<A>);
 
 #[derive(Error, Debug)]
 pub enum ParsingError {
-    #[error("Recieved {0}: Only valid chars are A-Z")]
-    Charset(char),
-    #[error("Recieved {0}: Only valid positions are 0-25")]
-    InvalidPosition(usize),
+    #[error("Recieved {0}: Only valid chars are {1}")]
+    Charset(char, &'static str),
+    #[error("Recieved {0}: Only valid positions are 0-{1}")]
+    InvalidPosition(usize, usize),
+}
+
+/// Fails the build for an alphabet whose contacts do not fit in a `u8`, every character and position is made through
+/// a function that calls this
+const fn valid<A: Alphabet>() {
+    assert!(
+        A::SIZE > 0 && A::SIZE <= 256,
+        "An alphabet must have between 1 and 256 symbols"
+    );
 }
 
-impl TryFrom<char> for Character {
+impl<A: Alphabet> TryFrom<char> for Character<A> {
     type Error = ParsingError;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
-        let value_uppercase = value.to_ascii_uppercase();
-        match value_uppercase {
-            'A'..='Z' => Ok(Character(value_uppercase)),
-            _ => Err(ParsingError::Charset(value)),
+        const { valid::<A>() };
+        match A::index(value) {
+            Some(i) => Ok(Character(i, PhantomData)),
+            None => Err(ParsingError::Charset(value, A::NAME)),
         }
     }
 }
 
-impl TryFrom<u8> for Position {
+impl<A: Alphabet> TryFrom<u8> for Position<A> {
     type Error = ParsingError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0..=25 => Ok(Position(value)),
-            _ => Err(ParsingError::InvalidPosition(value as usize)),
+        const { valid::<A>() };
+        match (value as usize) < A::SIZE {
+            true => Ok(Position(value, PhantomData)),
+            false => Err(ParsingError::InvalidPosition(value as usize, A::SIZE - 1)),
         }
     }
 }
 
-impl TryFrom<char> for Position {
+impl<A: Alphabet> TryFrom<char> for Position<A> {
     type Error = ParsingError;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
-        let v = value.to_ascii_uppercase();
-        let offset = v as u8 - b'A';
-        match Position::try_from(offset) {
-            Ok(p) => Ok(p),
-            Err(_) => Err(ParsingError::Charset(value)),
-        }
+        let offset = Character::<A>::try_from(value)?.get_offset();
+        Ok(Position(offset, PhantomData))
     }
 }
 
-impl Add<usize> for Position {
-    type Output = Position;
+impl<A: Alphabet> Add<usize> for Position<A> {
+    type Output = Position<A>;
 
     fn add(self, rhs: usize) -> Self::Output {
-        let offset = (self.0 as usize + rhs) % 26;
-        Position((offset as usize).try_into().unwrap())
+        let offset = (self.0 as usize + rhs) % A::SIZE;
+        Position(offset as u8, PhantomData)
     }
 }
 
-impl Sub<Position> for Character {
-    type Output = Self;
+impl<A: Alphabet> Sub<Position<A>> for Position<A> {
+    type Output = Position<A>;
 
-    fn sub(self, rhs: Position) -> Self::Output {
-        // -25..=-1 if rhs is bigger, otherwise 0..=25
-        let offset: isize = (self.get_offset() as isize - rhs.0 as isize) % 26;
+    fn sub(self, rhs: Position<A>) -> Self::Output {
+        Position(
+            ((A::SIZE + self.0 as usize - rhs.0 as usize) % A::SIZE) as u8,
+            PhantomData,
+        )
+    }
+}
 
-        let result = match offset {
-            -25..=-1 => (26 + offset) as u8 + b'A',
-            0..=25 => offset as u8 + b'A',
-            _ => unreachable!(),
-        };
+impl<A: Alphabet> Sub<Position<A>> for Character<A> {
+    type Output = Self;
 
-        /*#[cfg(test)]
-        println!(
-            "SUB<Position> for Character: self:{self} rhs:{rhs:?} offset:{offset} result:{result}"
-        );*/
-        Character::try_from(result as char).unwrap()
+    fn sub(self, rhs: Position<A>) -> Self::Output {
+        let offset = (A::SIZE + self.0 as usize - rhs.0 as usize) % A::SIZE;
+        Character(offset as u8, PhantomData)
     }
 }
 
-impl Add<Position> for Character {
-    type Output = Character;
+impl<A: Alphabet> Add<Position<A>> for Character<A> {
+    type Output = Character<A>;
 
-    fn add(self, rhs: Position) -> Self::Output {
-        let offset: u8 = (rhs.0 + self.get_offset()) % 26 + b'A';
-        Character::try_from(offset as char).unwrap()
+    fn add(self, rhs: Position<A>) -> Self::Output {
+        let offset = (self.0 as usize + rhs.0 as usize) % A::SIZE;
+        Character(offset as u8, PhantomData)
     }
 }
 
-impl Position {
+impl<A: Alphabet> Position<A> {
     pub fn advance(&mut self) {
-        self.0 = self.0 + 1_u8
+        *self = *self + 1
     }
 }
 
-impl Character {
+impl<A: Alphabet> Character<A> {
+    /// Index of the contact of this character, `A` is 0
     pub fn get_offset(&self) -> u8 {
-        self.0 as u8 - b'A'
+        self.0
+    }
+
+    /// Every character of the alphabet in the order of the contacts
+    pub fn all() -> impl Iterator<Item = Character<A>> + Clone {
+        const { valid::<A>() };
+        (0..A::SIZE).map(|i| Character(i as u8, PhantomData))
     }
 }
 
-impl Display for Character {
+impl<A: Alphabet> Display for Character<A> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        write!(f, "{}", self.0)
+        write!(f, "{}", char::from(*self))
     }
 }
 
-impl Into<char> for Character {
-    fn into(self) -> char {
-        self.0
+impl<A: Alphabet> From<Character<A>> for char {
+    fn from(c: Character<A>) -> char {
+        A::SYMBOLS[c.0 as usize]
+    }
+}
+
+impl<A: Alphabet> From<Position<A>> for char {
+    /// The symbol shown in the window at this position
+    fn from(p: Position<A>) -> char {
+        A::SYMBOLS[p.0 as usize]
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_conversion)]
 mod tests_character {
 
     use super::Character;
     use super::Position;
+    use crate::alphabet::Latin;
 
     #[test]
     fn construct_uppercase() {
         ('A'..='Z').into_iter().for_each(|c| {
-            Character::try_from(c).unwrap();
+            Character::<Latin>::try_from(c).unwrap();
         })
     }
 
     #[test]
     fn construct_lowercase() {
         ('a'..='z').into_iter().for_each(|c| {
-            Character::try_from(c).unwrap();
+            Character::<Latin>::try_from(c).unwrap();
         })
     }
 
@@ -145,7 +170,7 @@ mod tests_character {
     fn add_position() {
         ('A'..='Z')
             .into_iter()
-            .map(|c| Character::try_from(c).unwrap())
+            .map(|c| Character::<Latin>::try_from(c).unwrap())
             .for_each(|c| {
                 (0..25)
                     .into_iter()
@@ -193,34 +218,36 @@ mod tests_character {
 
     #[test]
     fn ca_pz_spot_test() {
-        let p = Position(25);
-        let c = Character('A');
-        assert!(c + p == Character('Z'))
+        let p = Position::<Latin>::try_from(25).unwrap();
+        let c = Character::try_from('A').unwrap();
+        assert!(c + p == Character::try_from('Z').unwrap())
     }
 
     #[test]
     fn cb_pz_bound_wrap() {
-        let p = Position(25);
-        let c = Character('B');
-        assert!(c + p == Character('A'))
+        let p = Position::<Latin>::try_from(25).unwrap();
+        let c = Character::try_from('B').unwrap();
+        assert!(c + p == Character::try_from('A').unwrap())
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_conversion, clippy::single_match)]
 mod tests_position {
 
     use super::Position;
+    use crate::alphabet::Latin;
 
     #[test]
     fn construct_0_25() {
         (0..=25).into_iter().for_each(|n| {
-            Position::try_from(n).unwrap();
+            Position::<Latin>::try_from(n).unwrap();
         })
     }
 
     #[test]
     fn too_large() {
-        match Position::try_from(26) {
+        match Position::<Latin>::try_from(26) {
             Ok(_) => panic!("Should not be able to construct position larger than 25"),
             Err(_) => (),
         }
@@ -229,16 +256,24 @@ mod tests_position {
     #[test]
     fn add() {
         (0..25).into_iter().for_each(|n| {
-            let p = Position(n);
+            let p = Position::<Latin>::try_from(n).unwrap();
             let r = p + 1;
-            assert!(r == Position(n + 1))
+            assert!(r == Position::try_from(n + 1).unwrap())
         })
     }
 
+    #[test]
+    fn sub() {
+        let p = Position::<Latin>::try_from(3).unwrap();
+        let position = |n| Position::try_from(n).unwrap();
+        assert!(p - position(1) == position(2));
+        assert!(p - position(4) == position(25));
+    }
+
     #[test]
     fn add_wrap() {
-        let p = Position::try_from(25).unwrap();
+        let p = Position::<Latin>::try_from(25).unwrap();
         let r = p + 1;
-        assert!(r == Position(0))
+        assert!(r == Position::try_from(0).unwrap())
     }
 }
@@ -1,62 +1,311 @@
 use crate::{
+    alphabet::{Alphabet, Latin},
     cipher::{Decode, Encode},
-    common::{Character, ParsingError},
-    plugboard::Plugboard,
-    reflector::{Reflector, Reflectors},
+    common::{Character, ParsingError, Position},
+    entry::EntryWheel,
+    model::{MachineModel, ModelError},
+    plugboard::Stecker,
+    reflector::Reflector,
     rotor::RotorConfig,
+    stream::EncodeWriter,
+    transliteration::Transliteration,
 };
+use bruh_moment::Bruh;
+use itertools::Itertools;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use strum_macros::{Display, EnumIter, EnumString};
+use thiserror::Error;
 
-pub struct Enigma {
-    rotors: RotorConfig,
-    plugboard: Plugboard,
-    reflector: Reflector,
+pub struct Enigma<A: Alphabet = Latin> {
+    rotors: RotorConfig<A>,
+    plugboard: Option<Stecker<A>>,
+    entry: EntryWheel<A>,
+    reflector: Reflector<A>,
+    presses: usize,
+    non_alphabetic: NonAlphabetic,
+    transliteration: Transliteration,
 }
 
-impl Enigma {
-    pub fn new(rotors: RotorConfig, plugboard: Plugboard, reflector: Reflectors) -> Enigma {
-        let reflector = Reflector::from(reflector);
+/// What `encode` does with characters that are not part of the alphabet of the machine, such as spaces and
+/// punctuation. None of them move the rotors unless they are replaced with `X`
+#[derive(
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum NonAlphabetic {
+    /// Left in the text as they are
+    #[default]
+    Passthrough,
+    /// Removed from the text
+    Strip,
+    /// Enciphered as an `X`, the way operators wrote punctuation
+    ReplaceX,
+    /// Encoding fails
+    Reject,
+}
+
+#[derive(Error, Debug)]
+pub enum EncodeError {
+    #[error("Recieved {0}: Only the characters {1} can be enciphered")]
+    NonAlphabetic(char, &'static str),
+    #[error("Recieved {0}: X is not one of the characters {1} and can not replace it")]
+    NoX(char, &'static str),
+}
+
+/// A character of the message, either enciphered with the given key press or kept as it is
+enum Key<A: Alphabet> {
+    Press(Character<A>, usize),
+    Keep(char),
+}
 
+impl<A: Alphabet> Enigma<A> {
+    /// `plugboard` is either a `Plugboard` or an `Uhr` plugged in its place
+    /// `reflector` is either one of the fixed `Reflectors` or a `Reflector` such as a wired UKW-D
+    /// The machine uses the alphabetical entry wheel of the military machines, see `with_entry_wheel`
+    pub fn new(
+        rotors: RotorConfig<A>,
+        plugboard: impl Into<Stecker<A>>,
+        reflector: impl Into<Reflector<A>>,
+    ) -> Enigma<A> {
         Enigma {
-            rotors: rotors,
-            plugboard: plugboard,
-            reflector: reflector,
+            plugboard: Some(plugboard.into()),
+            ..Enigma::without_plugboard(rotors, reflector)
         }
     }
 
-    fn encode_at(&self, c: Character, n: usize) -> Character {
-        let plugboard_enc = self.plugboard.encode(c);
-        let rotor_enc = self.rotors.encode_at(plugboard_enc, n);
-        let reflector_enc = self.reflector.encode(rotor_enc);
+    /// A machine without a plugboard, such as the commercial and railway machines
+    pub fn without_plugboard(
+        rotors: RotorConfig<A>,
+        reflector: impl Into<Reflector<A>>,
+    ) -> Enigma<A> {
+        Enigma {
+            rotors,
+            plugboard: None,
+            entry: EntryWheel::identity(),
+            reflector: reflector.into(),
+            presses: 0,
+            non_alphabetic: NonAlphabetic::default(),
+            transliteration: Transliteration::default(),
+        }
+    }
+
+    /// Handles characters outside the alphabet with `policy` instead of passing them through
+    pub fn with_non_alphabetic(self, policy: NonAlphabetic) -> Enigma<A> {
+        Enigma {
+            non_alphabetic: policy,
+            ..self
+        }
+    }
+
+    pub fn non_alphabetic(&self) -> NonAlphabetic {
+        self.non_alphabetic
+    }
+
+    /// Rewrites the text with `transliteration` before it is enciphered, for example to spell out umlauts
+    pub fn with_transliteration(self, transliteration: Transliteration) -> Enigma<A> {
+        Enigma {
+            transliteration,
+            ..self
+        }
+    }
+
+    /// Replaces the entry wheel, for example with the QWERTZ entry wheel of the commercial machines
+    pub fn with_entry_wheel(self, entry: impl Into<EntryWheel<A>>) -> Enigma<A> {
+        Enigma {
+            entry: entry.into(),
+            ..self
+        }
+    }
+
+    /// Lets the reflector move with the rotors, as on the Enigma G, starting from the position it was set to
+    pub fn with_stepping_reflector(self) -> Enigma<A> {
+        Enigma {
+            rotors: self.rotors.drive_reflector(self.reflector.position()),
+            ..self
+        }
+    }
+
+    pub(crate) fn rotors(&self) -> &RotorConfig<A> {
+        &self.rotors
+    }
+
+    pub(crate) fn plugboard(&self) -> Option<&Stecker<A>> {
+        self.plugboard.as_ref()
+    }
+
+    pub(crate) fn entry(&self) -> &EntryWheel<A> {
+        &self.entry
+    }
+
+    pub(crate) fn reflector(&self) -> &Reflector<A> {
+        &self.reflector
+    }
+
+    fn encode_at(&self, c: Character<A>, n: usize) -> Character<A> {
+        let plugboard_enc = self.plugboard.as_ref().map_or(c, |p| p.encode(c));
+        let entry_enc = self.entry.encode(plugboard_enc);
+        let rotor_enc = self.rotors.encode_at(entry_enc, n);
+        let reflector_position = self
+            .rotors
+            .reflector_position_at(n)
+            .unwrap_or(self.reflector.position());
+        let reflector_enc = self.reflector.encode_at(rotor_enc, reflector_position);
         let rotor_dec = self.rotors.decode_at(reflector_enc, n);
-        let plugboard_dec = self.plugboard.decode(rotor_dec);
-        plugboard_dec
-    }
-
-    pub fn encode(&self, s: &String) -> String {
-        s.par_char_indices()
-            .map(|(i, c)| (i, Character::try_from(c)))
-            .map(|(n, c)| match c {
-                Ok(plain) => self.encode_at(plain, n).into(),
-                Err(e) => match e {
-                    ParsingError::Charset(non_alphabetic) => non_alphabetic,
-                    _ => unreachable!(),
+        let entry_dec = self.entry.decode(rotor_dec);
+        self.plugboard
+            .as_ref()
+            .map_or(entry_dec, |p| p.decode(entry_dec))
+    }
+
+    /// Presses the key `c` and returns the lamp that lights up. The rotors step before the key is enciphered
+    pub fn press(&mut self, c: Character<A>) -> Character<A> {
+        let lamp = self.encode_at(c, self.presses);
+        self.presses += 1;
+        lamp
+    }
+
+    /// The letters in the windows, read from left to right so the slow rotor comes first. A reflector that moves with
+    /// the rotors is shown left of them
+    pub fn window(&self) -> String {
+        self.rotors
+            .positions_after(self.presses)
+            .into_iter()
+            .rev()
+            .map(char::from)
+            .collect()
+    }
+
+    /// Turns the rotors to `window`, which is read like `Enigma::window`. Key presses continue from the new
+    /// positions, and `encode` starts from them too
+    pub fn set_positions(&mut self, window: &str) -> Result<(), Bruh> {
+        let positions: Vec<Position<A>> =
+            window.chars().rev().map(Position::try_from).try_collect()?;
+        self.rotors.set_positions(&positions)?;
+        self.presses = 0;
+        Ok(())
+    }
+
+    /// Enciphers every symbol of the alphabet in `s`, after the transliteration set with `with_transliteration`
+    /// Any other character is handled as `Enigma::non_alphabetic` says. Each symbol is enciphered with the next key
+    /// press, however many bytes the characters before it take up
+    ///
+    /// Panics if that is not possible, see `try_encode`
+    pub fn encode(&self, s: &str) -> String {
+        self.try_encode(s).unwrap()
+    }
+
+    /// Like `encode`, but fails instead of panicking if `s` holds a character outside the alphabet that the
+    /// `NonAlphabetic` policy rejects or can not replace
+    pub fn try_encode(&self, s: &str) -> Result<String, EncodeError> {
+        self.encode_from(s, 0).map(|(ciphertext, _)| ciphertext)
+    }
+
+    /// Enciphers the text from `reader` into `writer` a buffer at a time, so the message never has to fit in memory
+    /// The ciphertext is the same as `encode` would produce for the whole message. Returns the number of bytes read
+    pub fn encode_stream(&self, mut reader: impl Read, writer: impl Write) -> io::Result<u64> {
+        let mut encoder = EncodeWriter::new(self, writer);
+        let read = io::copy(&mut reader, &mut encoder)?;
+        encoder.finish()?;
+        Ok(read)
+    }
+
+    /// Like `try_encode` for a part of a message that comes after `offset` key presses
+    /// Returns the ciphertext and the number of key presses once the part is enciphered
+    pub(crate) fn encode_from(
+        &self,
+        s: &str,
+        offset: usize,
+    ) -> Result<(String, usize), EncodeError> {
+        // Every key press depends on the characters before it, so they are counted before enciphering in parallel
+        let mut presses = offset;
+        let mut press = |c: Character<A>| {
+            presses += 1;
+            Ok(Some(Key::Press(c, presses - 1)))
+        };
+        let keys: Vec<Key<A>> = self
+            .transliteration
+            .apply(s)
+            .chars()
+            .map(|c| match Character::try_from(c) {
+                Ok(plain) => press(plain),
+                Err(ParsingError::Charset(c, alphabet)) => match self.non_alphabetic {
+                    NonAlphabetic::Passthrough => Ok(Some(Key::Keep(c))),
+                    NonAlphabetic::Strip => Ok(None),
+                    NonAlphabetic::ReplaceX => match Character::try_from('X') {
+                        Ok(x) => press(x),
+                        Err(_) => Err(EncodeError::NoX(c, alphabet)),
+                    },
+                    NonAlphabetic::Reject => Err(EncodeError::NonAlphabetic(c, alphabet)),
                 },
+                Err(ParsingError::InvalidPosition(..)) => unreachable!(),
             })
-            .collect()
+            .flatten_ok()
+            .try_collect()?;
+
+        let ciphertext = keys
+            .into_par_iter()
+            .map(|key| match key {
+                Key::Press(c, n) => self.encode_at(c, n).into(),
+                Key::Keep(c) => c,
+            })
+            .collect();
+        Ok((ciphertext, presses))
+    }
+}
+
+impl Enigma {
+    /// Builds the machine as `model`, with the entry wheel and the rotor drive of that model and a stepping reflector
+    /// if the model has one. `plugboard` is `None` for models without a plugboard
+    /// Fails if any of the components did not fit in the model
+    pub fn for_model(
+        model: MachineModel,
+        rotors: RotorConfig,
+        plugboard: Option<Stecker>,
+        reflector: impl Into<Reflector>,
+    ) -> Result<Enigma, ModelError> {
+        let rotors = rotors.with_drive(model.drive());
+        let enigma = Enigma {
+            plugboard,
+            ..Enigma::without_plugboard(rotors, reflector)
+        }
+        .with_entry_wheel(model.entry_wheel());
+        let enigma = match model.stepping_reflector() {
+            true => enigma.with_stepping_reflector(),
+            false => enigma,
+        };
+        model.check(&enigma)?;
+        Ok(enigma)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
+        alphabet::{Alphabet, Digits},
         common::Character,
-        plugboard::{Plugboard, Plugs},
-        reflector::Reflectors,
-        rotor::{RotorConfig, Rotors},
+        entry::EntryWheels,
+        model::{MachineModel, ModelError},
+        plugboard::{Plug, Plugboard, Plugs},
+        reflector::{Reflector, Reflectors},
+        rotor::{CustomRotor, Rotor, RotorConfig, RotorKind, Rotors, ZRotors},
+        transliteration::Transliteration,
+        uhr::Uhr,
     };
+    use itertools::Itertools;
 
-    use super::Enigma;
+    use super::{EncodeError, Enigma, NonAlphabetic};
 
     fn construct_enigma() -> Enigma {
         let rotors: RotorConfig =
@@ -72,9 +321,8 @@ mod tests {
     #[test]
     fn codec() {
         let e = construct_enigma();
-        (0..1000).into_iter().for_each(|n| {
+        (0..1000).for_each(|n| {
             ('A'..='Z')
-                .into_iter()
                 .map(|c| Character::try_from(c).unwrap())
                 .for_each(|c| {
                     let ct = e.encode_at(c, n);
@@ -94,4 +342,492 @@ mod tests {
 
         assert_eq!(plaintext, result)
     }
+
+    #[test]
+    fn known_ciphertext() {
+        // Enigma I, rotors I II III, rings AAA, positions AAA, reflector B
+        let rotors =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        let plugboard = Plugboard::try_from(Plugs::try_from(vec![]).unwrap()).unwrap();
+        let e = Enigma::new(rotors, plugboard, Reflectors::B);
+
+        assert_eq!(e.encode("AAAAA"), "BDZGO");
+        assert_eq!(e.encode("HELLOWORLD"), "ILBDAAMTAZ");
+    }
+
+    #[test]
+    fn barbarossa() {
+        // Operation Barbarossa, 1941: rotors II IV V, rings BUL, message key BLA
+        let rotors = RotorConfig::try_from([
+            (Rotors::V, 'A', 'L'),
+            (Rotors::IV, 'L', 'U'),
+            (Rotors::II, 'B', 'B'),
+        ])
+        .unwrap();
+        let plugs: Vec<Plug> = ["AV", "BS", "CG", "DL", "FU", "HZ", "IN", "KM", "OW", "RX"]
+            .iter()
+            .map(|p| {
+                let mut c = p.chars().map(|c| Character::try_from(c).unwrap());
+                Plug::try_from((c.next().unwrap(), c.next().unwrap())).unwrap()
+            })
+            .collect();
+        let plugboard = Plugboard::try_from(Plugs::try_from(plugs).unwrap()).unwrap();
+        let e = Enigma::new(rotors, plugboard, Reflectors::B);
+
+        let ciphertext = "EDPUDNRGYSZRCXNUYTPOMRMBOFKTBZREZKMLXLVEFGUEYSIOZVEQMIKUBPMMYLKLTTDEISMDICAGYKUACTCDOMOHWXMUUIAUBSTSLRNBZSZWNRFXWFYSSXJZVIJHIDISHPRKLKAYUPADTXQSPINQMATLPIFSVKDASCTACDPBOPVHJK";
+        let plaintext = "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZXSEBEZXUAFFLIEGERSTRASZERIQTUNGXDUBROWKIXDUBROWKIXOPOTSCHKAXOPOTSCHKAXUMXEINSAQTDREINULLXUHRANGETRETENXANGRIFFXINFXRGTX";
+
+        assert_eq!(e.encode(ciphertext), plaintext);
+    }
+
+    #[test]
+    fn m4_compatible_with_m3() {
+        // Beta at A with ring A and reflector B-thin behaves like reflector B on a three rotor machine
+        let rotors = RotorConfig::try_from([
+            (Rotors::III, 'A'),
+            (Rotors::II, 'A'),
+            (Rotors::I, 'A'),
+            (Rotors::Beta, 'A'),
+        ])
+        .unwrap();
+        let plugboard = Plugboard::try_from(Plugs::try_from(vec![]).unwrap()).unwrap();
+        let e = Enigma::new(rotors, plugboard, Reflectors::BThin);
+
+        assert_eq!(e.encode("AAAAA"), "BDZGO");
+    }
+
+    #[test]
+    fn m4_known_ciphertext() {
+        // U-boat message from Kapitänleutnant Looks, 1942: Beta II IV I, rings AAAV, positions VJNA, reflector B-thin
+        let rotors = RotorConfig::try_from([
+            (Rotors::I, 'A', 'V'),
+            (Rotors::IV, 'N', 'A'),
+            (Rotors::II, 'J', 'A'),
+            (Rotors::Beta, 'V', 'A'),
+        ])
+        .unwrap();
+        let plugs: Vec<Plug> = ["AT", "BL", "DF", "GJ", "HM", "NW", "OP", "QY", "RZ", "VX"]
+            .iter()
+            .map(|p| {
+                let mut c = p.chars().map(|c| Character::try_from(c).unwrap());
+                Plug::try_from((c.next().unwrap(), c.next().unwrap())).unwrap()
+            })
+            .collect();
+        let plugboard = Plugboard::try_from(Plugs::try_from(plugs).unwrap()).unwrap();
+        let e = Enigma::new(rotors, plugboard, Reflectors::BThin);
+
+        let ciphertext = "NCZWVUSXPNYMINHZXMQXSFWXWLKJAHSHNMCOCCAKUQPMKCSMHKSEINJUSBLKIOSXCKUBHMLLXCSJUSRRDVKOHULXWCCBGVLIYXEOAHXRHKKFVDREWEZLXOBAFGYUJQUKGRTVUKAMEURBVEKSUHHVOYHABCJWMAKLFKLMYFVNRIZRVVRTKOFDANJMOLBGFFLEOPRGTFLVRHOWOPBEKVWMUQFMPWPARMFHAGKXIIBG";
+        let plaintext = "VONVONJLOOKSJHFFTTTEINSEINSDREIZWOYYQNNSNEUNINHALTXXBEIANGRIFFUNTERWASSERGEDRUECKTYWABOSXLETZTERGEGNERSTANDNULACHTDREINULUHRMARQUANTONJOTANEUNACHTSEYHSDREIYZWOZWONULGRADYACHTSMYSTOSSENACHXEKNSVIERMBFAELLTYNNNNNNOOOVIERYSICHTEINSNULL";
+
+        assert_eq!(e.encode(ciphertext), plaintext);
+    }
+
+    #[test]
+    fn m4_codec() {
+        let rotors = RotorConfig::try_from([
+            (Rotors::VIII, 'R', 'C'),
+            (Rotors::VI, 'J', 'Z'),
+            (Rotors::V, 'N', 'A'),
+            (Rotors::Gamma, 'V', 'E'),
+        ])
+        .unwrap();
+        let plugboard = Plugboard::try_from(Plugs::try_from(vec![]).unwrap()).unwrap();
+        let e = Enigma::new(rotors, plugboard, Reflectors::CThin);
+
+        let plaintext =
+            "VONVONJLOOKSJHFFTTTEINSEINSDREIZWOYYQNNSNEUNINHALTXXBEIANGRIFFUNTERWASSERGEDRUECKTY";
+        assert_eq!(e.encode(&e.encode(plaintext)), plaintext);
+    }
+
+    #[test]
+    fn ukw_d_codec() {
+        let rotors =
+            RotorConfig::try_from([(Rotors::I, 'Q'), (Rotors::V, 'E'), (Rotors::III, 'V')])
+                .unwrap();
+        let plugboard = Plugboard::try_from(Plugs::try_from(vec![]).unwrap()).unwrap();
+        let pairs: Vec<(Character, Character)> = "AFBPCGDQENHXIWKSLTMROUVZ"
+            .chars()
+            .map(|c| Character::try_from(c).unwrap())
+            .tuples()
+            .collect();
+        let e = Enigma::new(rotors, plugboard, Reflector::ukw_d(&pairs).unwrap());
+
+        let plaintext = "ANXOBERKOMMANDODERLUFTWAFFE";
+        let ciphertext = e.encode(plaintext);
+        // From the same separate simulation as the Enigma G vector
+        assert_eq!(ciphertext, "NMPNMIOJYLORCPNLLSYVCHBIHUH");
+        assert_eq!(e.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn qwertz_entry_wheel() {
+        let e = construct_enigma();
+        let qwertz = construct_enigma().with_entry_wheel(EntryWheels::Qwertz);
+        let alphabetical = construct_enigma().with_entry_wheel(EntryWheels::Alphabetical);
+
+        let plaintext = "DIEWALZENLAGEWIRDTAEGLICHGEWECHSELT";
+        let ciphertext = qwertz.encode(plaintext);
+
+        assert_eq!(alphabetical.encode(plaintext), e.encode(plaintext));
+        assert_ne!(ciphertext, e.encode(plaintext));
+        assert_eq!(qwertz.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn reflector_position() {
+        let construct = |position| {
+            let reflector = Reflector::from(Reflectors::B)
+                .with_position(position)
+                .unwrap();
+            let rotors =
+                RotorConfig::try_from([(Rotors::I, 'A'), (Rotors::II, 'X'), (Rotors::IV, 'N')])
+                    .unwrap();
+            let plugboard = Plugboard::try_from(Plugs::try_from(vec![]).unwrap()).unwrap();
+            Enigma::new(rotors, plugboard, reflector)
+        };
+        let plaintext = "REFLECTORSETTING";
+
+        assert_eq!(
+            construct('A').encode(plaintext),
+            construct_enigma().encode(plaintext)
+        );
+        let ciphertext = construct('K').encode(plaintext);
+        assert_ne!(ciphertext, construct('A').encode(plaintext));
+        assert_eq!(construct('K').encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn stepping_reflector() {
+        let construct = || {
+            let reflector = Reflector::from(Reflectors::B).with_position('K').unwrap();
+            let rotors =
+                RotorConfig::try_from([(Rotors::I, 'A'), (Rotors::II, 'E'), (Rotors::IV, 'I')])
+                    .unwrap();
+            let plugboard = Plugboard::try_from(Plugs::try_from(vec![]).unwrap()).unwrap();
+            Enigma::new(rotors, plugboard, reflector)
+        };
+        let fixed = construct();
+        let stepping = construct().with_stepping_reflector();
+
+        // The double step turns rotor IV to its notch on the first key press, the reflector moves on the second
+        let plaintext = "A".repeat(600);
+        let ciphertext = stepping.encode(&plaintext);
+        assert_eq!(ciphertext[..1], fixed.encode(&plaintext)[..1]);
+        assert_ne!(ciphertext[1..2], fixed.encode(&plaintext)[1..2]);
+        assert_eq!(stepping.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn enigma_g() {
+        let construct = |model| {
+            let reflector = Reflector::from(Reflectors::G).with_position('F').unwrap();
+            let rotors = RotorConfig::try_from([
+                (Rotors::GII, 'Z', 'C'),
+                (Rotors::GIII, 'T', 'A'),
+                (Rotors::GI, 'L', 'Q'),
+            ])
+            .unwrap();
+            Enigma::for_model(model, rotors, None, reflector)
+        };
+        let g = construct(MachineModel::G).unwrap();
+        assert!(matches!(
+            construct(MachineModel::K),
+            Err(ModelError::Rotor(..))
+        ));
+
+        // Worked out with a separate simulation written from the machine's published description, not by this crate
+        assert_eq!(
+            g.encode(&"DIESXISTXEINEXPROBE".repeat(2)),
+            "NDZQVQHCPCWXSZFCQIXHDDDJDKYBKPAKOSDTWN"
+        );
+
+        let plaintext = "DIESXISTXEINEXPROBE".repeat(40);
+        let ciphertext = g.encode(&plaintext);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(g.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn uhr() {
+        let pairs = [
+            ('A', 'V'),
+            ('B', 'S'),
+            ('C', 'G'),
+            ('D', 'L'),
+            ('F', 'U'),
+            ('H', 'Z'),
+            ('I', 'N'),
+            ('K', 'M'),
+            ('O', 'W'),
+            ('R', 'X'),
+        ]
+        .map(|(a, b)| {
+            (
+                Character::try_from(a).unwrap(),
+                Character::try_from(b).unwrap(),
+            )
+        });
+        let construct = |dial| {
+            let rotors = RotorConfig::try_from([
+                (Rotors::V, 'A', 'L'),
+                (Rotors::IV, 'L', 'U'),
+                (Rotors::II, 'B', 'B'),
+            ])
+            .unwrap();
+            Enigma::new(rotors, Uhr::new(&pairs, dial).unwrap(), Reflectors::B)
+        };
+
+        // At dial position 00 the Uhr is the same as plug cables, so the Barbarossa message still decrypts
+        let e = construct(0);
+        assert_eq!(e.encode("EDPUDNRGYSZRCXNUYTPO"), "AUFKLXABTEILUNGXVONX");
+
+        let e = construct(27);
+        let plaintext = "DIESXISTXEINEXPROBE";
+        // From the same separate simulation as the Enigma G vector
+        assert_eq!(e.encode(plaintext), "SFBPQGIOINACAWBFTJH");
+        let ciphertext = e.encode(plaintext);
+        assert_ne!(ciphertext, construct(0).encode(plaintext));
+        assert_eq!(e.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn enigma_t() {
+        let reflector = Reflector::from(Reflectors::T).with_position('R').unwrap();
+        let rotors = RotorConfig::try_from([
+            (Rotors::TVIII, 'W', 'M'),
+            (Rotors::TI, 'Z', 'A'),
+            (Rotors::TV, 'K', 'Q'),
+        ])
+        .unwrap();
+        let e = Enigma::for_model(MachineModel::T, rotors, None, reflector).unwrap();
+
+        // From the same separate simulation as the Enigma G vector
+        assert_eq!(
+            e.encode(&"KOBEXANXTOKIOX".repeat(2)),
+            "TUXRIFFSVEFOZJEXROURUVWZFRQC"
+        );
+
+        let plaintext = "KOBEXANXTOKIOX".repeat(30);
+        let ciphertext = e.encode(&plaintext);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(e.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn without_plugboard() {
+        let rotors = || {
+            RotorConfig::try_from([(Rotors::I, 'A'), (Rotors::II, 'X'), (Rotors::IV, 'N')]).unwrap()
+        };
+        let e = Enigma::without_plugboard(rotors(), Reflectors::B);
+        let plaintext = "THE QUICK BROWN FOX JUMPED OVER THE LAZY DOG";
+
+        assert_eq!(e.encode(plaintext), construct_enigma().encode(plaintext));
+    }
+
+    #[test]
+    fn railway_and_swiss_k() {
+        let railway = Enigma::for_model(
+            MachineModel::Railway,
+            RotorConfig::try_from([
+                (Rotors::RailwayIII, 'B', 'Z'),
+                (Rotors::RailwayII, 'A', 'A'),
+                (Rotors::RailwayI, 'N', 'M'),
+            ])
+            .unwrap(),
+            None,
+            Reflector::from(Reflectors::Railway)
+                .with_position('J')
+                .unwrap(),
+        )
+        .unwrap();
+        let swiss_k = Enigma::for_model(
+            MachineModel::SwissK,
+            RotorConfig::try_from([
+                (Rotors::SwissKIII, 'B', 'Z'),
+                (Rotors::SwissKII, 'A', 'A'),
+                (Rotors::SwissKI, 'N', 'M'),
+            ])
+            .unwrap(),
+            None,
+            Reflector::from(Reflectors::K).with_position('J').unwrap(),
+        )
+        .unwrap();
+
+        // From the same separate simulation as the Enigma G vector
+        assert_eq!(
+            railway.encode("ZUGXNACHXBERLINXABFAHRTXNEUNXUHR"),
+            "MMWIQFZEFFQNHMMLMVSQUWIWLYIRTGLX"
+        );
+        assert_eq!(
+            swiss_k.encode("ZUGXNACHXBERLINXABFAHRTXNEUNXUHR"),
+            "KOERAZDJEKQUCOMTLTPOONMQVHKJRFDK"
+        );
+
+        let plaintext = "ZUGXNACHXBERLINXABFAHRTXNEUNXUHR".repeat(20);
+        [railway, swiss_k].iter().for_each(|e| {
+            let ciphertext = e.encode(&plaintext);
+            assert_ne!(ciphertext, plaintext);
+            assert_eq!(e.encode(&ciphertext), plaintext);
+        })
+    }
+
+    #[test]
+    fn enigma_z() {
+        let rotors = || {
+            RotorConfig::<Digits>::try_from([
+                (ZRotors::I, '3', '0'),
+                (ZRotors::II, '9', '4'),
+                (ZRotors::III, '1', '0'),
+            ])
+            .unwrap()
+        };
+        let e = Enigma::without_plugboard(rotors(), Reflector::z());
+        let plaintext = "0815 4711 1234567890 0000000000";
+
+        let ciphertext = e.encode(plaintext);
+        // From the same separate simulation as the Enigma G vector
+        assert_eq!(ciphertext, "2444 6877 5088951306 3928668296");
+        assert!(ciphertext.chars().all(|c| c.is_ascii_digit() || c == ' '));
+        assert_eq!(e.encode(&ciphertext), plaintext);
+        // A digit is never enciphered as itself
+        assert!(ciphertext
+            .chars()
+            .zip(plaintext.chars())
+            .all(|(c, p)| c == ' ' || c != p));
+    }
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    struct Alphanumeric;
+
+    impl Alphabet for Alphanumeric {
+        const SYMBOLS: &'static [char] = &[
+            'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q',
+            'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '0', '1', '2', '3', '4', '5', '6', '7',
+            '8', '9',
+        ];
+        const NAME: &'static str = "A-Z, 0-9";
+    }
+
+    #[test]
+    fn extended_alphabet() {
+        let symbols = Alphanumeric::SYMBOLS;
+        let wiring = |f: &dyn Fn(usize) -> usize| -> String {
+            (0..symbols.len())
+                .map(|i| symbols[f(i) % symbols.len()])
+                .collect()
+        };
+        let rotor = |k: usize, position: char| {
+            let custom =
+                CustomRotor::for_alphabet::<Alphanumeric>(&wiring(&|i| 5 * i + k), "Z9", None)
+                    .unwrap();
+            Rotor::<Alphanumeric>::try_from((RotorKind::Custom(custom), position, 'A')).unwrap()
+        };
+        let rotors =
+            RotorConfig::from_rotors(vec![rotor(1, '8'), rotor(2, 'Z'), rotor(3, 'A')]).unwrap();
+        let reflector = Reflector::from_wiring(&wiring(&|i| i + 18)).unwrap();
+        let e = Enigma::without_plugboard(rotors, reflector);
+        let plaintext = "MEET AT 0900 NEAR PIER 7";
+
+        let ciphertext = e.encode(plaintext);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(e.encode(&ciphertext), plaintext);
+        assert!(CustomRotor::for_alphabet::<Alphanumeric>(&wiring(&|i| 2 * i), "", None).is_err());
+        assert!(Reflector::<Alphanumeric>::from_wiring(&wiring(&|i| i + 1)).is_err());
+    }
+
+    #[test]
+    fn press() {
+        let rotors =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        let mut e = Enigma::without_plugboard(rotors, Reflectors::B);
+        let a = Character::try_from('A').unwrap();
+
+        assert_eq!(e.window(), "AAA");
+        let lamps: String = (0..5).map(|_| char::from(e.press(a))).collect();
+        assert_eq!(lamps, "BDZGO");
+        assert_eq!(e.window(), "AAF");
+        // Bulk encoding still starts from the positions the rotors were set to
+        assert_eq!(e.encode("AAAAA"), "BDZGO");
+    }
+
+    #[test]
+    fn set_positions() {
+        let rotors =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        let mut e = Enigma::without_plugboard(rotors, Reflectors::B);
+        let a = Character::try_from('A').unwrap();
+
+        (0..10).for_each(|_| {
+            e.press(a);
+        });
+        e.set_positions("ADU").unwrap();
+        assert_eq!(e.window(), "ADU");
+        e.press(a);
+        e.press(a);
+        e.press(a);
+        // The middle rotor double steps
+        assert_eq!(e.window(), "BFX");
+
+        assert!(e.set_positions("AD").is_err());
+        assert!(e.set_positions("AD1").is_err());
+        assert_eq!(e.window(), "BFX");
+    }
+
+    #[test]
+    fn non_alphabetic() {
+        let e = || {
+            let rotors =
+                RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                    .unwrap();
+            Enigma::without_plugboard(rotors, Reflectors::B)
+        };
+        let policy = |p| e().with_non_alphabetic(p);
+
+        // Spaces and punctuation do not move the rotors
+        assert_eq!(e().encode("AA AAA!"), "BD ZGO!");
+        assert_eq!(policy(NonAlphabetic::Strip).encode("AA AAA!"), "BDZGO");
+        assert_eq!(
+            policy(NonAlphabetic::ReplaceX).encode("HELLO.WORLD"),
+            e().encode("HELLOXWORLD")
+        );
+        assert!(matches!(
+            policy(NonAlphabetic::Reject).try_encode("AA AAA"),
+            Err(EncodeError::NonAlphabetic(' ', _))
+        ));
+        assert_eq!(policy(NonAlphabetic::Reject).encode("AAAAA"), "BDZGO");
+
+        let rotors = RotorConfig::<Digits>::try_from([
+            (ZRotors::I, '0', '0'),
+            (ZRotors::II, '0', '0'),
+            (ZRotors::III, '0', '0'),
+        ])
+        .unwrap();
+        let z = Enigma::without_plugboard(rotors, Reflector::z())
+            .with_non_alphabetic(NonAlphabetic::ReplaceX);
+        assert!(matches!(
+            z.try_encode("12.5"),
+            Err(EncodeError::NoX('.', _))
+        ));
+    }
+
+    #[test]
+    fn multi_byte() {
+        let e = || {
+            let rotors =
+                RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                    .unwrap();
+            Enigma::without_plugboard(rotors, Reflectors::B)
+        };
+
+        // Characters that take up several bytes do not skip any rotor positions
+        assert_eq!(e().encode("AÄA—AßAA"), "BÄD—ZßGO");
+        let plaintext = "DIE BRÜCKE — SÜDLICH VON GRÖßENBROCK";
+        assert_eq!(e().encode(&e().encode(plaintext)), plaintext);
+
+        let german = e().with_transliteration(Transliteration::German);
+        assert_eq!(german.encode("Brücke 12"), e().encode("BRUECKE EINSZWEI"));
+    }
 }
@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use bruh_moment::Bruh;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+use crate::{
+    alphabet::{Alphabet, Latin},
+    cipher::{Cipher, Decode, Encode},
+    common::Character,
+};
+
+/// The entry wheel (Eintrittswalze) connects the keys and the plugboard to the contacts of the first rotor
+pub struct EntryWheel<A: Alphabet = Latin> {
+    kind: Option<EntryWheels>,
+    cipher: Cipher<A>,
+}
+
+/// This enum represents each available entry wheel
+/// Military machines wire the keys to the contacts in alphabetical order, commercial machines such as the
+/// Enigma D and K in the order of the keyboard. The Enigma T has an entry wheel of its own
+#[derive(EnumIter, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum EntryWheels {
+    #[default]
+    Alphabetical,
+    Qwertz,
+    Tirpitz,
+}
+
+impl<A: Alphabet> EntryWheel<A> {
+    /// Generates an entry wheel from a wiring that lists the key connected to each contact from `A` to `Z`
+    pub fn new(wiring: &str) -> Result<EntryWheel<A>, Bruh> {
+        let cipher = Cipher::from_str(wiring)?;
+        Ok(EntryWheel { kind: None, cipher })
+    }
+
+    /// Wires every key to the contact of the same symbol
+    pub fn identity() -> EntryWheel<A> {
+        let cipher = Cipher::try_from(Character::all().collect::<Vec<Character<A>>>()).unwrap();
+        EntryWheel { kind: None, cipher }
+    }
+
+    /// The member of `EntryWheels` this entry wheel was made from, if any
+    pub fn kind(&self) -> Option<EntryWheels> {
+        self.kind
+    }
+}
+
+impl From<EntryWheels> for EntryWheel {
+    /// Returns a pre-generated entry wheel given a member of the `EntryWheels` enum
+    fn from(e: EntryWheels) -> Self {
+        let wheel = match e {
+            EntryWheels::Alphabetical => EntryWheel::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap(),
+            EntryWheels::Qwertz => EntryWheel::new("QWERTZUIOASDFGHJKPYXCVBNML").unwrap(),
+            EntryWheels::Tirpitz => EntryWheel::new("KZROUQHYAIGBLWVSTDXFPNMCJE").unwrap(),
+        };
+        EntryWheel {
+            kind: Some(e),
+            ..wheel
+        }
+    }
+}
+
+impl<A: Alphabet> Encode<A> for EntryWheel<A> {
+    /// Maps a key to the rotor contact it is wired to
+    fn encode(&self, c: Character<A>) -> Character<A> {
+        self.cipher.decode(c)
+    }
+}
+
+impl<A: Alphabet> Decode<A> for EntryWheel<A> {
+    /// Maps a rotor contact back to its key
+    fn decode(&self, c: Character<A>) -> Character<A> {
+        self.cipher.encode(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alphabet::Latin,
+        cipher::{Decode, Encode},
+        common::Character,
+    };
+
+    use super::{EntryWheel, EntryWheels};
+
+    #[test]
+    fn alphabetical() {
+        let etw = EntryWheel::from(EntryWheels::Alphabetical);
+        ('A'..='Z')
+            .map(|c| Character::try_from(c).unwrap())
+            .for_each(|c| assert_eq!(etw.encode(c), c))
+    }
+
+    #[test]
+    fn qwertz() {
+        // The first key of the keyboard is wired to the first contact
+        let etw = EntryWheel::from(EntryWheels::Qwertz);
+        let q = Character::try_from('Q').unwrap();
+        let a = Character::try_from('A').unwrap();
+        let l = Character::try_from('L').unwrap();
+        let z = Character::try_from('Z').unwrap();
+
+        assert_eq!(etw.encode(q), a);
+        assert_eq!(etw.encode(l), z);
+        assert_eq!(etw.decode(a), q);
+    }
+
+    #[test]
+    fn tirpitz() {
+        let etw = EntryWheel::from(EntryWheels::Tirpitz);
+        let k = Character::try_from('K').unwrap();
+        let a = Character::try_from('A').unwrap();
+        let e = Character::try_from('E').unwrap();
+        let z = Character::try_from('Z').unwrap();
+
+        assert_eq!(etw.encode(k), a);
+        assert_eq!(etw.encode(e), z);
+    }
+
+    #[test]
+    fn codec() {
+        let etw = EntryWheel::from(EntryWheels::Qwertz);
+        ('A'..='Z')
+            .map(|c| Character::try_from(c).unwrap())
+            .for_each(|c| assert_eq!(etw.decode(etw.encode(c)), c))
+    }
+
+    #[test]
+    fn invalid_wiring() {
+        assert!(EntryWheel::<Latin>::new("QWERTZUIOASDFGHJKPYXCVBNMM").is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::transliteration::SPELLED_DIGITS;
+
+/// The letters on the top row of the keyboard, in the order of the digits they stand for
+const TOP_ROW: [char; 10] = ['P', 'Q', 'W', 'E', 'R', 'T', 'Z', 'U', 'I', 'O'];
+
+/// The groups ciphertext was sent in
+#[derive(
+    EnumIter, EnumString, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Grouping {
+    /// Five letter groups, used by the Army and the Air Force
+    Army,
+    /// Four letter groups, used by the Navy
+    Navy,
+}
+
+impl Grouping {
+    pub fn size(&self) -> usize {
+        match self {
+            Grouping::Army => 5,
+            Grouping::Navy => 4,
+        }
+    }
+
+    /// Writes everything but whitespace in groups separated by spaces, `per_line` groups to a line
+    pub fn group(&self, s: &str, per_line: usize) -> String {
+        let symbols: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        symbols
+            .chunks(self.size() * per_line.max(1))
+            .map(|line| {
+                line.chunks(self.size())
+                    .map(String::from_iter)
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Removes the spaces and line breaks between the groups of a message
+pub fn ungroup(s: &str) -> String {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// How numbers are written in the plaintext
+#[derive(
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Numbers {
+    /// Each digit is typed with the letter above it on the top row of the keyboard, `QWERTZUIOP`, and the number is
+    /// enclosed in `Y`, so `17` becomes `YQUY`
+    #[default]
+    TopRow,
+    /// Each digit is spelled out in German, so `17` becomes `EINSSIEBEN`
+    Spelled,
+}
+
+/// A convention that writes a single letter. Restoring a decrypted message can not tell it apart from the same letter
+/// in a word, so it is only undone when asked for with `Conventions::with_restored`
+#[derive(
+    EnumIter, EnumString, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Letter {
+    /// `X` for a full stop
+    FullStop,
+    /// `Y` for a comma
+    Comma,
+    /// `Q` for `CH`
+    Ch,
+}
+
+/// The conventions operators wrote plaintext with: `X` for a full stop, `Y` for a comma, doubled letters for other
+/// separators (`XX` for a colon, `YY` for a dash and `KK` around parentheses), `Q` for `CH` and numbers written with
+/// letters.
+/// Restoring a decrypted message undoes the doubled separators and the numbers written with the top row, but leaves
+/// the single `Letter` conventions alone unless they are asked for, so that `QUELLE` does not come back as
+/// `CHUELLE`. Spelled out numbers are left spelled out
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Conventions {
+    numbers: Numbers,
+    restored: Vec<Letter>,
+}
+
+impl Conventions {
+    pub fn new(numbers: Numbers) -> Conventions {
+        Conventions {
+            numbers,
+            restored: Vec::new(),
+        }
+    }
+
+    /// Also undoes the single `letters` when restoring, for messages where those letters never appear in a word
+    pub fn with_restored(self, letters: impl IntoIterator<Item = Letter>) -> Conventions {
+        Conventions {
+            restored: letters.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Writes the plaintext in capitals the way an operator would have. Whitespace and characters without a
+    /// convention are kept
+    pub fn prepare(&self, s: &str) -> String {
+        let mut prepared = String::with_capacity(s.len());
+        let mut chars = s.chars().flat_map(char::to_uppercase).peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                'C' if chars.next_if_eq(&'H').is_some() => prepared.push('Q'),
+                '0'..='9' => match self.numbers {
+                    Numbers::TopRow => {
+                        prepared.push('Y');
+                        prepared.push(TOP_ROW[digit(c)]);
+                        while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                            prepared.push(TOP_ROW[digit(d)]);
+                        }
+                        prepared.push('Y');
+                    }
+                    Numbers::Spelled => prepared.push_str(SPELLED_DIGITS[digit(c)]),
+                },
+                _ => match separator(c) {
+                    Some(letters) => prepared.push_str(letters),
+                    None => prepared.push(c),
+                },
+            }
+        }
+        prepared
+    }
+
+    /// Undoes the conventions on a decrypted message, as far as they can be told apart from the text. The single
+    /// letters are only undone if they were asked for with `with_restored`
+    pub fn restore(&self, s: &str) -> String {
+        let chars: Vec<char> = s.chars().collect();
+        let mut restored = String::with_capacity(s.len());
+        let mut parenthesis = false;
+        let mut rest = &chars[..];
+        while let [c, tail @ ..] = rest {
+            if let Some(number) = self.top_row_number(rest) {
+                rest = &rest[number.len() + 2..];
+                restored.push_str(&number);
+                continue;
+            }
+
+            let (text, len) = match rest {
+                ['X', 'X', ..] => (":", 2),
+                ['Y', 'Y', ..] => ("-", 2),
+                ['K', 'K', ..] => {
+                    parenthesis = !parenthesis;
+                    (if parenthesis { "(" } else { ")" }, 2)
+                }
+                ['X', ..] if self.restored.contains(&Letter::FullStop) => (".", 1),
+                ['Y', ..] if self.restored.contains(&Letter::Comma) => (",", 1),
+                ['Q', ..] if self.restored.contains(&Letter::Ch) => ("CH", 1),
+                _ => {
+                    restored.push(*c);
+                    rest = tail;
+                    continue;
+                }
+            };
+            restored.push_str(text);
+            rest = &rest[len..];
+        }
+        restored
+    }
+
+    /// The digits of a number written with the top row at the start of `s`, enclosed in `Y`
+    fn top_row_number(&self, s: &[char]) -> Option<String> {
+        if self.numbers != Numbers::TopRow {
+            return None;
+        }
+        let ['Y', tail @ ..] = s else {
+            return None;
+        };
+        let digits: String = tail
+            .iter()
+            .map_while(|c| TOP_ROW.iter().position(|d| d == c))
+            .map(|d| char::from(b'0' + d as u8))
+            .collect();
+        match tail.get(digits.len()) {
+            Some('Y') if !digits.is_empty() => Some(digits),
+            _ => None,
+        }
+    }
+}
+
+fn digit(c: char) -> usize {
+    c as usize - '0' as usize
+}
+
+/// The letters an operator typed for a punctuation mark
+fn separator(c: char) -> Option<&'static str> {
+    match c {
+        '.' => Some("X"),
+        ',' => Some("Y"),
+        ':' => Some("XX"),
+        '-' => Some("YY"),
+        '(' | ')' => Some("KK"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::{ungroup, Conventions, Grouping, Letter, Numbers};
+
+    #[test]
+    fn army_groups() {
+        assert_eq!(
+            Grouping::Army.group("QWERTZ UIOPAS\nDFGHJKL", 2),
+            "QWERT ZUIOP\nASDFG HJKL"
+        );
+    }
+
+    #[test]
+    fn navy_groups() {
+        assert_eq!(Grouping::Navy.group("QWERTZUIOP", 10), "QWER TZUI OP");
+    }
+
+    #[test]
+    fn ungroup_groups() {
+        assert_eq!(ungroup("QWERT ZUIOP\nASDFG"), "QWERTZUIOPASDFG");
+    }
+
+    #[test]
+    fn prepare() {
+        let conventions = Conventions::default();
+
+        assert_eq!(
+            conventions.prepare("Nach 17 Uhr: Angriff (Nord-Ost), Ende."),
+            "NAQ YQUY UHRXX ANGRIFF KKNORDYYOSTKKY ENDEX"
+        );
+    }
+
+    #[test]
+    fn prepare_spelled() {
+        let conventions = Conventions::new(Numbers::Spelled);
+
+        assert_eq!(conventions.prepare("Punkt 20."), "PUNKT ZWEINULLX");
+    }
+
+    #[test]
+    fn restore() {
+        let conventions = Conventions::default();
+        let prepared = conventions.prepare("NACH 17 UHR: ANGRIFF (NORD-OST), ENDE.");
+
+        // Only the numbers and the doubled separators are undone by default
+        assert_eq!(
+            conventions.restore(&prepared),
+            "NAQ 17 UHR: ANGRIFF (NORD-OST)Y ENDEX"
+        );
+
+        let conventions = conventions.with_restored(Letter::iter());
+        assert_eq!(
+            conventions.restore(&prepared),
+            "NACH 17 UHR: ANGRIFF (NORD-OST), ENDE."
+        );
+        assert_eq!(
+            conventions.restore("NAQYQUYUHRXXANGRIFFX"),
+            "NACH17UHR:ANGRIFF."
+        );
+    }
+
+    #[test]
+    fn restore_words() {
+        // Q, X and Y that belong to words are left alone
+        let conventions = Conventions::default();
+        let prepared = conventions.prepare("QUELLE BEI XANTEN: 12 KM YPERN");
+
+        assert_eq!(prepared, "QUELLE BEI XANTENXX YQWY KM YPERN");
+        assert_eq!(
+            conventions.restore(&prepared),
+            "QUELLE BEI XANTEN: 12 KM YPERN"
+        );
+
+        let conventions = conventions.with_restored([Letter::FullStop]);
+        assert_eq!(conventions.restore("QUELLEX"), "QUELLE.");
+    }
+
+    #[test]
+    fn restore_spelled() {
+        let conventions = Conventions::new(Numbers::Spelled);
+        assert_eq!(conventions.restore("YQUYZWEIX"), "YQUYZWEIX");
+
+        // Without top row numbers `Y` is always a comma
+        let conventions = conventions.with_restored(Letter::iter());
+        assert_eq!(conventions.restore("YQUYZWEIX"), ",CHU,ZWEI.");
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt::{Display, Formatter};
+
+use bruh_moment::Bruh;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+use thiserror::Error;
+
+use crate::{alphabet::Alphabet, enigma::Enigma, format::ungroup};
+
+/// How the message key is sent at the start of a message
+/// The operator chose a message key for every message, sent it enciphered in the indicator and enciphered the body
+/// with the rotors set to the message key
+#[derive(
+    EnumIter, EnumString, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Procedure {
+    /// Until May 1940 the message key was typed twice with the rotors set to the daily ground setting
+    /// (Grundstellung), which gives a six letter indicator on a three rotor machine
+    Doubled,
+    /// Later the operator chose a start position for every message, sent it in clear and typed the message key once
+    /// with the rotors set to it
+    Chosen,
+}
+
+/// The indicator at the start of a message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Indicator {
+    /// The message key enciphered twice at the ground setting
+    Doubled(String),
+    /// The start position in clear, and the message key enciphered once at that position
+    Chosen { start: String, key: String },
+}
+
+#[derive(Error, Debug)]
+pub enum IndicatorError {
+    #[error("Recieved a message key of {0} letters, the machine shows {1}")]
+    KeyLength(usize, usize),
+    #[error("Recieved a message of {0} letters, the indicator alone takes {1}")]
+    Short(usize, usize),
+    #[error("The doubled indicator deciphers to {0}, which does not repeat a message key")]
+    Garbled(String),
+    #[error("The doubled indicator is enciphered at the ground setting, which was not given")]
+    NoGround,
+}
+
+impl Procedure {
+    /// Enciphers `message_key` into an indicator. `setting` is the ground setting for the doubled procedure, or the
+    /// start position the operator chose. Leaves the machine turned to `setting`
+    pub fn indicate<A: Alphabet>(
+        &self,
+        enigma: &mut Enigma<A>,
+        setting: &str,
+        message_key: &str,
+    ) -> Result<Indicator, Bruh> {
+        let width = enigma.window().chars().count();
+        let length = message_key.chars().count();
+        if length != width {
+            return Err(IndicatorError::KeyLength(length, width).into());
+        }
+
+        enigma.set_positions(setting)?;
+        Ok(match self {
+            Procedure::Doubled => Indicator::Doubled(enigma.try_encode(&message_key.repeat(2))?),
+            Procedure::Chosen => Indicator::Chosen {
+                start: setting.to_string(),
+                key: enigma.try_encode(message_key)?,
+            },
+        })
+    }
+
+    /// Enciphers `plaintext` with the rotors set to `message_key`, and returns it with the indicator that is sent in
+    /// front of it. `setting` is used as in `Procedure::indicate`. Leaves the machine turned to the message key
+    pub fn encipher<A: Alphabet>(
+        &self,
+        enigma: &mut Enigma<A>,
+        setting: &str,
+        message_key: &str,
+        plaintext: &str,
+    ) -> Result<(Indicator, String), Bruh> {
+        let indicator = self.indicate(enigma, setting, message_key)?;
+        enigma.set_positions(message_key)?;
+        Ok((indicator, enigma.try_encode(plaintext)?))
+    }
+
+    /// Reads the indicator at the start of an intercepted `message`, recovers the message key and deciphers the body
+    /// behind it. `ground` is only needed for the doubled procedure. Returns the message key and the plaintext, and
+    /// leaves the machine turned to the message key
+    pub fn decipher<A: Alphabet>(
+        &self,
+        enigma: &mut Enigma<A>,
+        ground: Option<&str>,
+        message: &str,
+    ) -> Result<(String, String), Bruh> {
+        let (indicator, body) = Indicator::parse(*self, enigma, message)?;
+        let message_key = indicator.message_key(enigma, ground)?;
+        enigma.set_positions(&message_key)?;
+        let plaintext = enigma.try_encode(&body)?;
+        Ok((message_key, plaintext))
+    }
+}
+
+impl Indicator {
+    /// Splits the indicator off the start of `message`, which may be written in groups. Returns the indicator and the
+    /// body without the spaces between groups
+    pub fn parse<A: Alphabet>(
+        procedure: Procedure,
+        enigma: &Enigma<A>,
+        message: &str,
+    ) -> Result<(Indicator, String), IndicatorError> {
+        let width = enigma.window().chars().count();
+        let letters: Vec<char> = ungroup(message).chars().collect();
+        if letters.len() < 2 * width {
+            return Err(IndicatorError::Short(letters.len(), 2 * width));
+        }
+
+        let (header, body) = letters.split_at(2 * width);
+        let indicator = match procedure {
+            Procedure::Doubled => Indicator::Doubled(header.iter().collect()),
+            Procedure::Chosen => Indicator::Chosen {
+                start: header[..width].iter().collect(),
+                key: header[width..].iter().collect(),
+            },
+        };
+        Ok((indicator, body.iter().collect()))
+    }
+
+    pub fn procedure(&self) -> Procedure {
+        match self {
+            Indicator::Doubled(_) => Procedure::Doubled,
+            Indicator::Chosen { .. } => Procedure::Chosen,
+        }
+    }
+
+    /// Deciphers the message key. `ground` is the ground setting the doubled indicator was enciphered at, and is not
+    /// used for the later procedure. Leaves the machine turned to the setting the key was enciphered at
+    pub fn message_key<A: Alphabet>(
+        &self,
+        enigma: &mut Enigma<A>,
+        ground: Option<&str>,
+    ) -> Result<String, Bruh> {
+        match self {
+            Indicator::Doubled(key) => {
+                enigma.set_positions(ground.ok_or(IndicatorError::NoGround)?)?;
+                let doubled: Vec<char> = enigma.try_encode(key)?.chars().collect();
+                let (first, second) = doubled.split_at(doubled.len() / 2);
+                match first == second {
+                    true => Ok(first.iter().collect()),
+                    false => Err(IndicatorError::Garbled(doubled.iter().collect()).into()),
+                }
+            }
+            Indicator::Chosen { start, key } => {
+                enigma.set_positions(start)?;
+                Ok(enigma.try_encode(key)?)
+            }
+        }
+    }
+}
+
+impl Display for Indicator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Indicator::Doubled(key) => write!(f, "{key}"),
+            Indicator::Chosen { start, key } => write!(f, "{start} {key}"),
+        }
+    }
+}
+
+/// A random setting for the `width` windows of a machine, to be used as a message key or start position
+pub fn random_setting<A: Alphabet>(width: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..width)
+        .map(|_| A::SYMBOLS[rng.gen_range(0..A::SIZE)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alphabet::Latin,
+        enigma::Enigma,
+        reflector::Reflectors,
+        rotor::{RotorConfig, Rotors},
+    };
+
+    use super::{random_setting, Indicator, Procedure};
+
+    fn construct_enigma() -> Enigma {
+        let rotors =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        Enigma::without_plugboard(rotors, Reflectors::B)
+    }
+
+    #[test]
+    fn doubled() {
+        let mut e = construct_enigma();
+
+        // Typing AAAAAA at AAA on rotors I, II and III lights up BDZGOW
+        let indicator = Procedure::Doubled.indicate(&mut e, "AAA", "AAA").unwrap();
+        assert_eq!(indicator, Indicator::Doubled("BDZGOW".to_string()));
+        assert_eq!(indicator.message_key(&mut e, Some("AAA")).unwrap(), "AAA");
+        assert!(indicator.message_key(&mut e, None).is_err());
+        assert!(indicator.message_key(&mut e, Some("AAB")).is_err());
+    }
+
+    #[test]
+    fn chosen() {
+        let mut e = construct_enigma();
+
+        let indicator = Procedure::Chosen.indicate(&mut e, "AAA", "AAA").unwrap();
+        assert_eq!(
+            indicator,
+            Indicator::Chosen {
+                start: "AAA".to_string(),
+                key: "BDZ".to_string()
+            }
+        );
+        assert_eq!(indicator.to_string(), "AAA BDZ");
+        assert_eq!(indicator.message_key(&mut e, None).unwrap(), "AAA");
+    }
+
+    #[test]
+    fn key_length() {
+        let mut e = construct_enigma();
+
+        assert!(Procedure::Chosen.indicate(&mut e, "AAA", "AB").is_err());
+        assert!(Procedure::Chosen.indicate(&mut e, "AA", "ABC").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut e = construct_enigma();
+
+        for procedure in [Procedure::Doubled, Procedure::Chosen] {
+            let (indicator, ciphertext) = procedure
+                .encipher(&mut e, "QWE", "RTZ", "ANGRIFFUMVIERUHR")
+                .unwrap();
+            let message = format!("{indicator} {ciphertext}");
+            let (key, plaintext) = procedure.decipher(&mut e, Some("QWE"), &message).unwrap();
+
+            assert_eq!(key, "RTZ");
+            assert_eq!(plaintext, "ANGRIFFUMVIERUHR");
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let e = construct_enigma();
+
+        let (indicator, body) = Indicator::parse(Procedure::Chosen, &e, "QWE RTZUI OPASD").unwrap();
+        assert_eq!(indicator.procedure(), Procedure::Chosen);
+        assert_eq!(indicator.to_string(), "QWE RTZ");
+        assert_eq!(body, "UIOPASD");
+        assert!(Indicator::parse(Procedure::Doubled, &e, "QWERT").is_err());
+    }
+
+    #[test]
+    fn random() {
+        let setting = random_setting::<Latin>(4);
+
+        assert_eq!(setting.len(), 4);
+        assert!(setting.chars().all(|c| c.is_ascii_uppercase()));
+    }
+}
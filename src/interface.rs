@@ -1,25 +1,49 @@
+use anyhow::{anyhow, Context};
 use bruh_moment::Bruh;
 use clap::Parser;
-use inquire::{validator::ExactLengthValidator, MultiSelect, Select};
+use inquire::{validator::ExactLengthValidator, Confirm, MultiSelect, Select};
 use itertools::Itertools;
 use libenigma::{
     common::Character,
-    enigma::Enigma,
-    plugboard::{Plug, Plugboard, Plugs},
-    reflector::Reflectors,
-    rotor::{RotorConfig, Rotors},
+    enigma::{Enigma, NonAlphabetic},
+    entry::EntryWheels,
+    format::{Grouping, Letter, Numbers},
+    indicator::Procedure,
+    keysheet::{date_from_days, KeySheet},
+    model::MachineModel,
+    plugboard::{Plug, Plugboard, Plugs, Stecker},
+    reflector::{Reflector, Reflectors},
+    rotor::{RotorConfig, RotorKind, Rotors},
+    stepping::Drive,
+    transliteration::Transliteration,
+    uhr::{Uhr, UHR_POSITIONS},
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use strum::IntoEnumIterator;
 
 #[derive(Parser, Debug)]
 #[command(version)]
 pub struct Args {
     /// Path to config file. If config file exists, that config will be used. Otherwise a config file will be generated
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "keyring")]
     pub config: Option<String>,
 
+    /// Path to a key sheet holding a month of daily keys, as YAML (.yaml or .yml), CSV (.csv) or the printed layout
+    /// (any other extension). The key for `--date` is used
+    #[arg(short, long)]
+    pub keyring: Option<String>,
+
+    /// Date of the key to take from the keyring, written like 1944-10-31. Defaults to today
+    #[arg(long, requires = "keyring")]
+    pub date: Option<String>,
+
     /// Path to output ciphertext
     #[arg(short, long)]
     pub output: Option<String>,
@@ -30,20 +54,166 @@ pub struct Args {
 
     /// (Optional) Plaintext to encode. Mutually exclusive with -i command line argument
     pub plaintext: Option<String>,
+
+    /// What happens to characters that can not be enciphered: passthrough, strip, replace-x or reject.
+    /// Overrides the config file
+    #[arg(long)]
+    pub non_alphabetic: Option<NonAlphabetic>,
+
+    /// Rewrites the plaintext before it is enciphered: none or german. Overrides the config file
+    #[arg(long)]
+    pub transliteration: Option<Transliteration>,
+
+    /// Writes the ciphertext in groups: army (five letters) or navy (four letters). When decrypting, the spaces and
+    /// line breaks between groups are removed first. Reads the whole input file
+    #[arg(long)]
+    pub grouping: Option<Grouping>,
+
+    /// Number of groups on each line of grouped ciphertext
+    #[arg(long, default_value_t = 10)]
+    pub groups_per_line: usize,
+
+    /// Writes the plaintext with operator conventions: X for a full stop, Q for CH and numbers either top-row or
+    /// spelled. When decrypting, the numbers and doubled separators are undone. Reads the whole input file
+    #[arg(long)]
+    pub conventions: Option<Numbers>,
+
+    /// Single letter conventions that are undone as well when decrypting, separated by commas: full-stop, comma or
+    /// ch. Only safe when those letters never appear in a word of the message
+    #[arg(long, value_delimiter = ',', requires = "conventions")]
+    pub restore: Vec<Letter>,
+
+    /// The input is ciphertext: grouping is removed before and conventions are undone after deciphering
+    #[arg(short, long)]
+    pub decrypt: bool,
+
+    /// Sends the message key in an indicator in front of the message: doubled or chosen. When decrypting, the
+    /// message key is read from the indicator. Reads the whole input file
+    #[arg(long)]
+    pub indicator: Option<Procedure>,
+
+    /// Ground setting (Grundstellung) the doubled indicator is enciphered at, or the start position for the chosen
+    /// indicator, which is random if not given
+    #[arg(long)]
+    pub ground: Option<String>,
+
+    /// Message key the body is enciphered with, random if not given
+    #[arg(long)]
+    pub message_key: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
-    reflector: Reflectors,
-    rotors: [(Rotors, char); 3],
+    /// Machine model the settings must fit, any combination of components is allowed without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<MachineModel>,
+    reflector: ReflectorSetting,
+    /// Three rotors, or four for the M4 where the last one is a Greek wheel
+    /// Each rotor is either the name of a built in rotor or a custom rotor `{name, wiring, notches}`
+    rotors: Vec<(RotorKind, char)>,
+    /// Ring setting (Ringstellung) for each rotor, in the same order as `rotors`. Missing ring settings are `A`
+    #[serde(default)]
+    rings: Vec<char>,
+    #[serde(default)]
     plugs: Vec<(char, char)>,
+    /// The Uhr switch box, plugged in instead of the plug cables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uhr: Option<UhrSetting>,
+    /// Entry wheel wiring, defaults to the entry wheel of the model or the alphabetical entry wheel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<EntryWheels>,
+    /// Position of a settable reflector
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reflector_position: Option<char>,
+    /// Whether the reflector moves with the rotors, defaults to what the model does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reflector_steps: Option<bool>,
+    /// How the rotors are moved, defaults to the drive of the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    drive: Option<Drive>,
+    /// What happens to spaces, punctuation and other characters that can not be enciphered
+    #[serde(default)]
+    non_alphabetic: NonAlphabetic,
+    /// Rewrites the plaintext before it is enciphered, for example German umlauts and digits
+    #[serde(default)]
+    transliteration: Transliteration,
+}
+
+/// Either one of the fixed reflectors, or the pairs wired into a UKW-D
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ReflectorSetting {
+    Fixed(Reflectors),
+    /// The 12 pairs of the rewirable UKW-D, J-Y is always wired
+    Rewirable {
+        ukw_d: Vec<(char, char)>,
+    },
+}
+
+/// Sockets of the `a` and `b` plug of each of the 10 pairs of the Uhr, starting with `1a` and `1b`, and the position
+/// of its dial from 0 to 39
+#[derive(Serialize, Deserialize)]
+pub struct UhrSetting {
+    pairs: Vec<(char, char)>,
+    dial: u8,
+}
+
+impl TryFrom<UhrSetting> for Uhr {
+    type Error = Bruh;
+
+    fn try_from(value: UhrSetting) -> Result<Self, Self::Error> {
+        let pairs: Vec<(Character, Character)> = value
+            .pairs
+            .iter()
+            .map(|(a, b)| Ok::<_, Bruh>((Character::try_from(*a)?, Character::try_from(*b)?)))
+            .try_collect()?;
+        Ok(Uhr::new(&pairs, value.dial)?)
+    }
+}
+
+impl TryFrom<ReflectorSetting> for Reflector {
+    type Error = Bruh;
+
+    fn try_from(value: ReflectorSetting) -> Result<Self, Self::Error> {
+        match value {
+            ReflectorSetting::Fixed(r) => Ok(Reflector::from(r)),
+            ReflectorSetting::Rewirable { ukw_d } => {
+                let pairs: Vec<(Character, Character)> = ukw_d
+                    .iter()
+                    .map(|(l, r)| {
+                        Ok::<_, Bruh>((Character::try_from(*l)?, Character::try_from(*r)?))
+                    })
+                    .try_collect()?;
+                Ok(Reflector::ukw_d(&pairs)?)
+            }
+        }
+    }
+}
+
+impl Display for ReflectorSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectorSetting::Fixed(r) => write!(f, "{r}"),
+            ReflectorSetting::Rewirable { .. } => write!(f, "D (rewirable)"),
+        }
+    }
 }
 
 impl TryFrom<Config> for Enigma {
     type Error = Bruh;
 
     fn try_from(value: Config) -> Result<Self, Self::Error> {
-        let rotor_config: RotorConfig = RotorConfig::try_from(value.rotors)?;
+        let rotors: Vec<(RotorKind, char, char)> = value
+            .rotors
+            .into_iter()
+            .enumerate()
+            .map(|(i, (r, p))| (r, p, value.rings.get(i).copied().unwrap_or('A')))
+            .collect();
+        let drive = value
+            .drive
+            .or(value.model.map(|m| m.drive()))
+            .unwrap_or_default();
+        let rotor_config: RotorConfig = RotorConfig::try_from(rotors)?.with_drive(drive);
         let plugs: Vec<Plug> = value
             .plugs
             .iter()
@@ -53,46 +223,138 @@ impl TryFrom<Config> for Enigma {
                     Character::try_from(*r).unwrap(),
                 )
             })
-            .map(|p| Plug::try_from(p))
+            .map(Plug::try_from)
             .try_collect()?;
-        let plugs: Plugs = Plugs::try_from(plugs)?;
-        let plugboard = Plugboard::try_from(plugs)?;
+        // The model decides how many plugs fit
+        let plugs: Plugs = match value.model.map(|model| (model, model.max_plugs())) {
+            Some((model, Some(max))) => Plugs::with_limit(plugs, max)
+                .with_context(|| format!("The {model} takes at most {max} plugs"))?,
+            _ => Plugs::try_from(plugs)?,
+        };
+        // Without plugs or an Uhr the machine has no plugboard
+        let plugboard: Option<Stecker> = match value.uhr {
+            Some(_) if !value.plugs.is_empty() => {
+                return Err(anyhow!("The Uhr is plugged in instead of the plug cables"))
+            }
+            Some(uhr) => Some(Uhr::try_from(uhr)?.into()),
+            None if value.plugs.is_empty() => None,
+            None => Some(Plugboard::try_from(plugs)?.into()),
+        };
+
+        let mut reflector = Reflector::try_from(value.reflector)?;
+        if let Some(position) = value.reflector_position {
+            reflector = reflector.with_position(position)?;
+        }
 
-        Ok(Enigma::new(rotor_config, plugboard, value.reflector))
+        let entry = value
+            .entry
+            .or(value.model.map(|m| m.entry_wheel()))
+            .unwrap_or_default();
+        let enigma = match plugboard {
+            Some(plugboard) => Enigma::new(rotor_config, plugboard, reflector),
+            None => Enigma::without_plugboard(rotor_config, reflector),
+        }
+        .with_entry_wheel(entry)
+        .with_non_alphabetic(value.non_alphabetic)
+        .with_transliteration(value.transliteration);
+        let reflector_steps = value
+            .reflector_steps
+            .or(value.model.map(|m| m.stepping_reflector()))
+            .unwrap_or_default();
+        let enigma = match reflector_steps {
+            true => enigma.with_stepping_reflector(),
+            false => enigma,
+        };
+        if let Some(model) = value.model {
+            model.check(&enigma)?;
+        }
+        Ok(enigma)
     }
 }
 
 impl Config {
     pub fn new() -> Config {
-        let rotors = Config::get_rotors();
-        let reflector = Config::get_reflector();
-        let plugs = Config::get_plugs();
+        let model = Config::get_model();
+        let rotors = Config::get_rotors(model);
+        let rings = Config::get_rings(&rotors);
+        let reflector = Config::get_reflector(model);
+        let reflector_position = Config::get_reflector_position(model);
+        let uhr = Config::get_uhr(model);
+        let plugs = match uhr {
+            Some(_) => vec![],
+            None => Config::get_plugs(model),
+        };
 
         Config {
-            reflector: reflector,
-            rotors: rotors,
-            plugs: plugs,
+            model: Some(model),
+            reflector,
+            rotors,
+            rings,
+            plugs,
+            uhr,
+            entry: None,
+            reflector_position,
+            reflector_steps: None,
+            drive: None,
+            non_alphabetic: NonAlphabetic::default(),
+            transliteration: Transliteration::default(),
         }
     }
 
-    fn get_reflector() -> Reflectors {
+    fn get_model() -> MachineModel {
+        println!("Machine Configuration:");
+        let options: Vec<MachineModel> = MachineModel::iter().collect();
+
+        Select::new("Select Machine", options).prompt().unwrap()
+    }
+
+    fn get_reflector(model: MachineModel) -> ReflectorSetting {
         println!("Reflector Configuration:");
-        let options: Vec<Reflectors> = Reflectors::iter().collect();
+        let mut options: Vec<ReflectorSetting> = model
+            .reflectors()
+            .iter()
+            .map(|r| ReflectorSetting::Fixed(*r))
+            .collect();
+        if model.ukw_d() {
+            options.push(ReflectorSetting::Rewirable { ukw_d: vec![] });
+        }
+
+        let ans = Select::new("Select Reflector", options).prompt().unwrap();
 
-        let ans = Select::new("Select Reflector", options).prompt();
+        match ans {
+            ReflectorSetting::Rewirable { .. } => {
+                let fixed: HashSet<char> = HashSet::from(['J', 'Y']);
+                ReflectorSetting::Rewirable {
+                    ukw_d: Config::get_pairs(12, "Select 2 characters to wire together", fixed),
+                }
+            }
+            fixed => fixed,
+        }
+    }
 
-        ans.unwrap()
+    fn get_reflector_position(model: MachineModel) -> Option<char> {
+        match model.settable_reflector() {
+            true => Some(
+                Select::new("Select reflector position", ('A'..='Z').collect_vec())
+                    .prompt()
+                    .unwrap(),
+            ),
+            false => None,
+        }
     }
 
-    fn get_rotors() -> [(Rotors, char); 3] {
+    fn get_rotors(model: MachineModel) -> Vec<(RotorKind, char)> {
         println!("Rotor Configuration:");
         let mut selectedrotors: HashSet<Rotors> = HashSet::new();
-        let options: Vec<Rotors> = Rotors::iter().collect();
         let position_options: Vec<char> = ('A'..='Z').collect_vec();
 
-        let mut ans: Vec<(Rotors, char)> = Vec::new();
-        for i in 0..3 {
-            // Get Rotor from user
+        let mut ans: Vec<(RotorKind, char)> = Vec::new();
+        for i in 0..model.rotor_count() {
+            // Get Rotor from user, the Greek wheel can only go in the fourth position
+            let options = match i {
+                3 => model.greek_wheels(),
+                _ => model.rotors(),
+            };
             let r: Rotors = *Select::new(
                 &format!("Select rotor for position {}", i + 1),
                 options
@@ -113,29 +375,87 @@ impl Config {
             .prompt()
             .unwrap();
 
-            ans.push((r, p));
+            ans.push((r.into(), p));
         }
-        ans.as_slice().try_into().unwrap()
+        ans
+    }
+
+    fn get_rings(rotors: &[(RotorKind, char)]) -> Vec<char> {
+        println!("Ring Settings:");
+        let ring_options: Vec<char> = ('A'..='Z').collect_vec();
+
+        rotors
+            .iter()
+            .map(|(r, _)| {
+                Select::new(
+                    &format!("Select ring setting for rotor '{r}'"),
+                    ring_options.clone(),
+                )
+                .prompt()
+                .unwrap()
+            })
+            .collect()
     }
 
-    fn get_plugs() -> Vec<(char, char)> {
+    fn get_plugs(model: MachineModel) -> Vec<(char, char)> {
+        let Some(max_plugs) = model.max_plugs() else {
+            return vec![];
+        };
         println!("Plugboard Configuration:");
         let num_plugs = {
-            let selection: Vec<usize> = (0..=10).into_iter().collect();
+            let selection: Vec<usize> = (0..=max_plugs).collect();
 
             Select::new("How many plugs do you want to use?", selection)
                 .prompt()
                 .unwrap()
         };
 
-        let mut plugs: Vec<(char, char)> = Vec::new();
-        let mut selected_chars: HashSet<char> = HashSet::new();
-        let options: Vec<char> = ('A'..='Z').into_iter().collect_vec();
+        Config::get_pairs(
+            num_plugs,
+            "Select 2 characters to use for plug",
+            HashSet::new(),
+        )
+    }
+
+    fn get_uhr(model: MachineModel) -> Option<UhrSetting> {
+        if !model.uhr() {
+            return None;
+        }
+        let use_uhr = Confirm::new("Plug in the Uhr instead of plug cables?")
+            .with_default(false)
+            .prompt()
+            .unwrap();
+        if !use_uhr {
+            return None;
+        }
+
+        println!("Uhr Configuration:");
+        // The first letter of each pair takes the `a` plug
+        let pairs = Config::get_pairs(
+            10,
+            "Select 2 characters for a pair of plugs",
+            HashSet::new(),
+        );
+        let dial = Select::new("Select dial position", (0..UHR_POSITIONS).collect())
+            .prompt()
+            .unwrap();
+        Some(UhrSetting { pairs, dial })
+    }
+
+    /// Prompts for `count` pairs of characters. Characters in `selected_chars` and characters that
+    /// were already paired are not offered again
+    fn get_pairs(
+        count: usize,
+        message: &str,
+        mut selected_chars: HashSet<char>,
+    ) -> Vec<(char, char)> {
+        let mut pairs: Vec<(char, char)> = Vec::new();
+        let options: Vec<char> = ('A'..='Z').collect_vec();
         let validator: ExactLengthValidator = ExactLengthValidator::new(2);
 
-        for _i in 0..num_plugs {
+        for _i in 0..count {
             let ans = MultiSelect::new(
-                "Select 2 characters to use for plug",
+                message,
                 options
                     .clone()
                     .into_iter()
@@ -150,10 +470,32 @@ impl Config {
                 selected_chars.insert(*c);
             }
 
-            let plug = (ans[0], ans[1]);
-            plugs.push(plug)
+            let pair = (ans[0], ans[1]);
+            pairs.push(pair)
         }
 
-        plugs
+        pairs
     }
 }
+
+/// Builds the machine for the key in the keyring at `path` that covers `date`, or today if no date is given
+pub fn keyring(path: &str, date: Option<&str>) -> Result<Enigma, Bruh> {
+    let content = fs::read_to_string(path)?;
+    let sheet = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("yaml" | "yml") => KeySheet::from_yaml(&content)?,
+        Some("csv") => KeySheet::from_csv(&content)?,
+        _ => KeySheet::from_text(&content)?,
+    };
+    let date = date.map(str::to_string).unwrap_or_else(today);
+    Enigma::try_from(sheet.key_on(&date)?)
+}
+
+/// Today's date in UTC, written like 1944-10-31
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        / 86_400;
+    date_from_days(days as i64)
+}
@@ -0,0 +1,640 @@
+use std::{collections::HashSet, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use bruh_moment::Bruh;
+use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    alphabet::{Alphabet, Latin},
+    common::Character,
+    enigma::Enigma,
+    model::MachineModel,
+    plugboard::{Plug, Plugboard, Plugs, Stecker},
+    reflector::Reflectors,
+    rotor::{RotorConfig, Rotors},
+};
+
+/// Number of plug cables the Army and the Navy used from 1939 on
+const PLUGS: usize = 10;
+
+/// Number of Kenngruppen on each day of a sheet
+const KENNGRUPPEN: usize = 4;
+
+/// Columns of a key sheet written as CSV
+const CSV_HEADER: &str = "date,model,reflector,rotors,rings,plugs,kenngruppen";
+
+/// Columns of a printed key sheet
+const TEXT_HEADER: [&str; 6] = [
+    "Tag",
+    "UKW",
+    "Walzenlage",
+    "Ringstellung",
+    "Steckerverbindungen",
+    "Kenngruppen",
+];
+
+/// A month of daily keys for one machine model
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct KeySheet {
+    year: u16,
+    month: u8,
+    keys: Vec<DailyKey>,
+}
+
+/// The key for one day of a key sheet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DailyKey {
+    pub day: u8,
+    pub model: MachineModel,
+    pub reflector: Reflectors,
+    /// Rotor order (Walzenlage), read from left to right like the sheet so the slow rotor comes first. On the M4 the
+    /// Greek wheel comes before it
+    pub rotors: Vec<Rotors>,
+    /// Ring settings (Ringstellung) in the same order as `rotors`
+    pub rings: Vec<char>,
+    /// Plug connections (Steckerverbindungen)
+    pub plugs: Vec<(char, char)>,
+    /// Trigrams that tell the receiver which key a message was enciphered with
+    pub kenngruppen: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum KeySheetError {
+    #[error("Recieved month {0}, months go from 1 to 12")]
+    Month(u8),
+    #[error("Recieved day {0}, {1}-{2:02} has {3} days")]
+    Day(u8, u16, u8, u8),
+    #[error("Day {0} has more than one key")]
+    DuplicateDay(u8),
+    #[error("Recieved keys for the {0} and the {1}, a sheet holds keys for one model")]
+    Model(MachineModel, MachineModel),
+    #[error("Recieved {0}, the date of a key is written like 1944-10-31")]
+    Date(String),
+    #[error("No key covers {0}")]
+    NoKey(String),
+    #[error("Recieved ring setting {1} for day {0}, rings are set from A to Z")]
+    Ring(u8, char),
+    #[error("The key for day {0} does not fit the machine: {1}")]
+    Key(u8, String),
+}
+
+impl KeySheet {
+    /// A sheet for `month` of `year` holding `keys`, at most one for each day. Fails unless every key builds a
+    /// machine of its model
+    pub fn new(year: u16, month: u8, keys: Vec<DailyKey>) -> Result<KeySheet, KeySheetError> {
+        let days = days_in_month(year, month)?;
+        let mut seen = HashSet::new();
+        for key in keys.iter() {
+            if key.day == 0 || key.day > days {
+                return Err(KeySheetError::Day(key.day, year, month, days));
+            }
+            if !seen.insert(key.day) {
+                return Err(KeySheetError::DuplicateDay(key.day));
+            }
+            if key.model != keys[0].model {
+                return Err(KeySheetError::Model(keys[0].model, key.model));
+            }
+            if let Some(ring) = key.rings.iter().find(|r| !r.is_ascii_uppercase()) {
+                return Err(KeySheetError::Ring(key.day, *ring));
+            }
+            if let Err(e) = Enigma::try_from(key) {
+                return Err(KeySheetError::Key(key.day, format!("{e:#}")));
+            }
+        }
+        Ok(KeySheet { year, month, keys })
+    }
+
+    /// A random key for every day of `month` that fits `model`. The same `seed` always gives the same sheet
+    pub fn generate(
+        model: MachineModel,
+        year: u16,
+        month: u8,
+        seed: Option<u64>,
+    ) -> Result<KeySheet, KeySheetError> {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let keys = (1..=days_in_month(year, month)?)
+            .map(|day| DailyKey::random(model, day, &mut rng))
+            .collect();
+        KeySheet::new(year, month, keys)
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn keys(&self) -> &[DailyKey] {
+        &self.keys
+    }
+
+    /// The key for `day` of the month, if the sheet has one
+    pub fn key(&self, day: u8) -> Option<&DailyKey> {
+        self.keys.iter().find(|k| k.day == day)
+    }
+
+    /// The key for `date`, written like `1944-10-31`. Fails if the sheet is for another month or has no key for
+    /// that day
+    pub fn key_on(&self, date: &str) -> Result<&DailyKey, KeySheetError> {
+        let (year, month, day) = parse_date(date)?;
+        match (year, month) == (self.year, self.month) {
+            true => self.key(day),
+            false => None,
+        }
+        .ok_or(KeySheetError::NoKey(date.to_string()))
+    }
+
+    pub fn to_yaml(&self) -> Result<String, Bruh> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    pub fn from_yaml(s: &str) -> Result<KeySheet, Bruh> {
+        let sheet: KeySheet = serde_yaml::from_str(s)?;
+        Ok(KeySheet::new(sheet.year, sheet.month, sheet.keys)?)
+    }
+
+    /// One line for each day with the date and model of the key. Lists are separated by spaces, so no field is quoted
+    pub fn to_csv(&self) -> String {
+        let rows = self.keys.iter().map(|k| {
+            [
+                format!("{}-{:02}-{:02}", self.year, self.month, k.day),
+                k.model.to_string(),
+                k.reflector.to_string(),
+                k.rotors.iter().join(" "),
+                k.rings.iter().join(" "),
+                k.plugs.iter().map(|(l, r)| format!("{l}{r}")).join(" "),
+                k.kenngruppen.join(" "),
+            ]
+            .join(",")
+        });
+        [CSV_HEADER.to_string()].into_iter().chain(rows).join("\n") + "\n"
+    }
+
+    pub fn from_csv(s: &str) -> Result<KeySheet, Bruh> {
+        let mut month = None;
+        let mut keys = vec![];
+        for (i, line) in s.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [date, model, reflector, rotors, rings, plugs, kenngruppen] = fields[..] else {
+                return Err(anyhow!("Line {}: expected the columns {CSV_HEADER}", i + 1));
+            };
+            let (year, m, day) = parse_date(date)?;
+            if *month.get_or_insert((year, m)) != (year, m) {
+                return Err(anyhow!(
+                    "Line {}: a sheet holds keys for one month, {date} is in another",
+                    i + 1
+                ));
+            }
+            let key = DailyKey::parse(day, model, reflector, rotors, rings, plugs, kenngruppen)
+                .with_context(|| format!("Line {}", i + 1))?;
+            keys.push(key);
+        }
+
+        let (year, month) = month.ok_or(anyhow!("The sheet holds no keys"))?;
+        Ok(KeySheet::new(year, month, keys)?)
+    }
+
+    /// The sheet laid out like the printed originals, with the last day of the month on top so used days could be
+    /// cut off. Ring settings are numbers and Kenngruppen are written in lowercase
+    pub fn to_text(&self) -> String {
+        let model = self
+            .keys
+            .first()
+            .map(|k| k.model.to_string())
+            .unwrap_or_default();
+        let rows: Vec<[String; 6]> = self
+            .keys
+            .iter()
+            .sorted_by_key(|k| std::cmp::Reverse(k.day))
+            .map(|k| {
+                [
+                    format!("{:02}", k.day),
+                    k.reflector.to_string(),
+                    k.rotors.iter().join(" "),
+                    k.rings
+                        .iter()
+                        .map(|r| match ring_number(*r) {
+                            Some(n) => format!("{n:02}"),
+                            None => r.to_string(),
+                        })
+                        .join(" "),
+                    k.plugs.iter().map(|(l, r)| format!("{l}{r}")).join(" "),
+                    k.kenngruppen.iter().map(|g| g.to_lowercase()).join(" "),
+                ]
+            })
+            .collect();
+        let header = TEXT_HEADER.map(String::from);
+        let widths: Vec<usize> = (0..TEXT_HEADER.len())
+            .map(|c| {
+                rows.iter()
+                    .chain([&header])
+                    .map(|r| r[c].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let mut lines = [&header].into_iter().chain(rows.iter()).map(|row| {
+            row.iter()
+                .zip(widths.iter())
+                .map(|(field, width)| format!("{field:<width$}"))
+                .join(" | ")
+                .trim_end()
+                .to_string()
+        });
+
+        format!(
+            "Geheime Kommandosache! {model} {}-{:02}\n{}\n",
+            self.year,
+            self.month,
+            lines.join("\n")
+        )
+    }
+
+    /// Reads a sheet written by `to_text`
+    pub fn from_text(s: &str) -> Result<KeySheet, Bruh> {
+        let mut lines = s.lines().filter(|l| !l.trim().is_empty());
+        let title: Vec<&str> = lines
+            .next()
+            .ok_or(anyhow!("The sheet is empty"))?
+            .split_whitespace()
+            .collect();
+        let [.., model, month] = title[..] else {
+            return Err(anyhow!(
+                "The first line must end with the model and the month"
+            ));
+        };
+        let (year, month, _) = parse_date(&format!("{month}-01"))?;
+
+        let keys: Vec<DailyKey> = lines
+            .skip(1)
+            .map(|line| {
+                let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+                let [day, reflector, rotors, rings, plugs, kenngruppen] = fields[..] else {
+                    return Err(anyhow!("Expected the columns {}", TEXT_HEADER.join(", ")));
+                };
+                let day = day.parse().context("Invalid day")?;
+                let rings = rings
+                    .split_whitespace()
+                    .map(|r| ring_letter(r).ok_or(anyhow!("Invalid ring setting {r}")))
+                    .collect::<Result<Vec<char>, Bruh>>()?
+                    .iter()
+                    .join(" ");
+                DailyKey::parse(day, model, reflector, rotors, &rings, plugs, kenngruppen)
+                    .with_context(|| format!("Day {day}"))
+            })
+            .try_collect()?;
+        Ok(KeySheet::new(year, month, keys)?)
+    }
+}
+
+impl DailyKey {
+    fn random(model: MachineModel, day: u8, rng: &mut StdRng) -> DailyKey {
+        let mut rotors: Vec<Rotors> = model.rotors().choose_multiple(rng, 3).copied().collect();
+        if let Some(greek) = model.greek_wheels().choose(rng) {
+            rotors.insert(0, *greek);
+        }
+        let rings = rotors.iter().map(|_| random_letter(rng)).collect();
+
+        let plugs = match model.max_plugs() {
+            Some(max) => {
+                let mut letters: Vec<char> = ('A'..='Z').collect();
+                letters.shuffle(rng);
+                letters
+                    .chunks(2)
+                    .take(PLUGS.min(max))
+                    .map(|p| (p[0], p[1]))
+                    .collect()
+            }
+            None => vec![],
+        };
+        let kenngruppen = (0..KENNGRUPPEN)
+            .map(|_| (0..3).map(|_| random_letter(rng)).collect())
+            .collect();
+
+        DailyKey {
+            day,
+            model,
+            reflector: *model.reflectors().choose(rng).unwrap(),
+            rotors,
+            rings,
+            plugs,
+            kenngruppen,
+        }
+    }
+
+    /// Reads the fields of a key written out as text, lists are separated by spaces
+    fn parse(
+        day: u8,
+        model: &str,
+        reflector: &str,
+        rotors: &str,
+        rings: &str,
+        plugs: &str,
+        kenngruppen: &str,
+    ) -> Result<DailyKey, Bruh> {
+        let letter = |s: &str| -> Result<char, Bruh> {
+            match s.chars().collect::<Vec<char>>()[..] {
+                [c] if c.is_ascii_alphabetic() => Ok(c.to_ascii_uppercase()),
+                _ => Err(anyhow!("Recieved {s}, expected a single letter")),
+            }
+        };
+        Ok(DailyKey {
+            day,
+            model: MachineModel::from_str(model).context("Invalid model")?,
+            reflector: Reflectors::from_str(reflector).context("Invalid reflector")?,
+            rotors: rotors
+                .split_whitespace()
+                .map(|r| Rotors::from_str(r).with_context(|| format!("Invalid rotor {r}")))
+                .try_collect()?,
+            rings: rings.split_whitespace().map(letter).try_collect()?,
+            plugs: plugs
+                .split_whitespace()
+                .map(|p| match p.chars().collect::<Vec<char>>()[..] {
+                    [l, r] => Ok((l, r)),
+                    _ => Err(anyhow!("Recieved plug {p}, a plug connects two letters")),
+                })
+                .try_collect()?,
+            kenngruppen: kenngruppen
+                .split_whitespace()
+                .map(str::to_uppercase)
+                .collect(),
+        })
+    }
+}
+
+impl TryFrom<&DailyKey> for Enigma {
+    type Error = Bruh;
+
+    /// Builds the machine for the key with every rotor at `A`
+    fn try_from(value: &DailyKey) -> Result<Self, Self::Error> {
+        if value.rings.len() != value.rotors.len() {
+            return Err(anyhow!(
+                "Recieved {} ring settings for {} rotors",
+                value.rings.len(),
+                value.rotors.len()
+            ));
+        }
+        let rotors: Vec<(Rotors, char, char)> = value
+            .rotors
+            .iter()
+            .zip(value.rings.iter())
+            .rev()
+            .map(|(r, ring)| (*r, 'A', *ring))
+            .collect();
+        let rotors = RotorConfig::try_from(rotors)?;
+
+        let plugs: Vec<Plug> = value
+            .plugs
+            .iter()
+            .map(|(l, r)| {
+                Ok::<_, Bruh>(Plug::try_from((
+                    Character::try_from(*l)?,
+                    Character::try_from(*r)?,
+                ))?)
+            })
+            .try_collect()?;
+        let plugboard: Option<Stecker> = match (value.model.max_plugs(), plugs.is_empty()) {
+            (None, true) => None,
+            (max, _) => {
+                let plugs = Plugs::with_limit(plugs, max.unwrap_or(0))?;
+                Some(Plugboard::try_from(plugs)?.into())
+            }
+        };
+
+        Ok(Enigma::for_model(
+            value.model,
+            rotors,
+            plugboard,
+            value.reflector,
+        )?)
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> Result<u8, KeySheetError> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => Ok(31),
+        4 | 6 | 9 | 11 => Ok(30),
+        2 if leap => Ok(29),
+        2 => Ok(28),
+        _ => Err(KeySheetError::Month(month)),
+    }
+}
+
+/// Reads a date written like `1944-10-31`
+fn parse_date(s: &str) -> Result<(u16, u8, u8), KeySheetError> {
+    let parts: Vec<&str> = s.split('-').collect();
+    match parts[..] {
+        [year, month, day] => match (year.parse(), month.parse(), day.parse()) {
+            (Ok(year), Ok(month), Ok(day)) => Ok((year, month, day)),
+            _ => Err(KeySheetError::Date(s.to_string())),
+        },
+        _ => Err(KeySheetError::Date(s.to_string())),
+    }
+}
+
+/// The date `days` days after 1970-01-01, written the way `parse_date` reads it
+pub fn date_from_days(days: i64) -> String {
+    // Counts in eras of 400 years starting on the 1st of March, so leap days come last
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9,
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year}-{month:02}-{day:02}")
+}
+
+fn random_letter(rng: &mut StdRng) -> char {
+    char::from(b'A' + rng.gen_range(0..26))
+}
+
+/// Rings were set by number on the printed sheets, `A` is 1
+fn ring_number(ring: char) -> Option<u8> {
+    Latin::index(ring).map(|i| i + 1)
+}
+
+fn ring_letter(number: &str) -> Option<char> {
+    match number.parse::<u8>() {
+        Ok(n @ 1..=26) => Some(char::from(b'A' + n - 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{enigma::Enigma, model::MachineModel, reflector::Reflectors, rotor::Rotors};
+    use itertools::Itertools;
+
+    use super::{date_from_days, parse_date, DailyKey, KeySheet};
+
+    fn key() -> DailyKey {
+        DailyKey {
+            day: 31,
+            model: MachineModel::EnigmaI,
+            reflector: Reflectors::B,
+            rotors: vec![Rotors::I, Rotors::IV, Rotors::III],
+            rings: vec!['P', 'Z', 'H'],
+            plugs: ["AD", "CN", "ET", "FL", "GI", "JV", "KZ", "PU", "QY", "WX"]
+                .iter()
+                .map(|p| (p.chars().next().unwrap(), p.chars().nth(1).unwrap()))
+                .collect(),
+            kenngruppen: vec!["JKM".into(), "OGI".into(), "NCJ".into(), "GLP".into()],
+        }
+    }
+
+    #[test]
+    fn generate() {
+        let sheet = KeySheet::generate(MachineModel::EnigmaI, 1944, 2, Some(7)).unwrap();
+
+        assert_eq!(sheet.keys().len(), 29);
+        assert_eq!(
+            sheet,
+            KeySheet::generate(MachineModel::EnigmaI, 1944, 2, Some(7)).unwrap()
+        );
+        sheet.keys().iter().for_each(|k| {
+            assert_eq!(k.rotors.iter().unique().count(), 3);
+            assert_eq!(k.plugs.len(), 10);
+            assert_eq!(k.kenngruppen.len(), 4);
+            assert!(Enigma::try_from(k).is_ok());
+        });
+        assert!(KeySheet::generate(MachineModel::EnigmaI, 1944, 13, None).is_err());
+    }
+
+    #[test]
+    fn generate_m4() {
+        let sheet = KeySheet::generate(MachineModel::M4, 1943, 3, Some(7)).unwrap();
+
+        sheet.keys().iter().for_each(|k| {
+            assert!(k.rotors[0].is_greek());
+            assert!(Enigma::try_from(k).is_ok());
+        });
+    }
+
+    #[test]
+    fn generate_without_plugboard() {
+        let sheet = KeySheet::generate(MachineModel::K, 1939, 4, None).unwrap();
+
+        sheet.keys().iter().for_each(|k| {
+            assert!(k.plugs.is_empty());
+            assert!(Enigma::try_from(k).is_ok());
+        });
+    }
+
+    #[test]
+    fn enigma() {
+        // The slow rotor is listed first, so the fast rotor III has ring setting H
+        let e = Enigma::try_from(&key()).unwrap();
+        assert_eq!(e.window(), "AAA");
+
+        let mut wrong = key();
+        wrong.rotors = vec![Rotors::I, Rotors::IV, Rotors::VIII];
+        assert!(Enigma::try_from(&wrong).is_err());
+    }
+
+    #[test]
+    fn new() {
+        assert!(KeySheet::new(1944, 10, vec![key()]).is_ok());
+        assert!(KeySheet::new(1944, 9, vec![key()]).is_err());
+        assert!(KeySheet::new(1944, 10, vec![key(), key()]).is_err());
+
+        let mut lowercase = key();
+        lowercase.rings = vec!['p', 'z', 'h'];
+        assert!(KeySheet::new(1944, 10, vec![lowercase]).is_err());
+        let mut number = key();
+        number.rings = vec!['1', '2', '3'];
+        assert!(KeySheet::new(1944, 10, vec![number]).is_err());
+        let mut two_rotors = key();
+        two_rotors.rotors.pop();
+        two_rotors.rings.pop();
+        assert!(KeySheet::new(1944, 10, vec![two_rotors]).is_err());
+        let mut plugged_twice = key();
+        plugged_twice.plugs[1] = ('A', 'N');
+        assert!(KeySheet::new(1944, 10, vec![plugged_twice]).is_err());
+    }
+
+    #[test]
+    fn key_on() {
+        let sheet = KeySheet::new(1944, 10, vec![key()]).unwrap();
+
+        assert_eq!(sheet.key_on("1944-10-31").unwrap(), &key());
+        assert!(sheet.key_on("1944-10-30").is_err());
+        assert!(sheet.key_on("1944-11-31").is_err());
+        assert!(sheet.key_on("31.10.1944").is_err());
+    }
+
+    #[test]
+    fn date() {
+        assert_eq!(date_from_days(0), "1970-01-01");
+        assert_eq!(date_from_days(11_016), "2000-02-29");
+        assert_eq!(date_from_days(20_088), "2024-12-31");
+        assert_eq!(parse_date(&date_from_days(20_088)).unwrap(), (2024, 12, 31));
+    }
+
+    #[test]
+    fn yaml() {
+        let sheet = KeySheet::generate(MachineModel::M3, 1941, 5, Some(3)).unwrap();
+
+        assert_eq!(
+            KeySheet::from_yaml(&sheet.to_yaml().unwrap()).unwrap(),
+            sheet
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let sheet = KeySheet::new(1944, 10, vec![key()]).unwrap();
+        let csv = sheet.to_csv();
+
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "1944-10-31,EnigmaI,B,I IV III,P Z H,AD CN ET FL GI JV KZ PU QY WX,JKM OGI NCJ GLP"
+        );
+        assert_eq!(KeySheet::from_csv(&csv).unwrap(), sheet);
+        assert_eq!(
+            KeySheet::from_csv(&csv.replace("P Z H", "p z h")).unwrap(),
+            sheet
+        );
+        assert!(KeySheet::from_csv(&csv.replace("P Z H", "1 2 3")).is_err());
+
+        let generated = KeySheet::generate(MachineModel::M4, 1943, 3, Some(1)).unwrap();
+        assert_eq!(KeySheet::from_csv(&generated.to_csv()).unwrap(), generated);
+    }
+
+    #[test]
+    fn text() {
+        let sheet = KeySheet::new(1944, 10, vec![key()]).unwrap();
+        let text = sheet.to_text();
+
+        assert_eq!(
+            text.lines().nth(2).unwrap(),
+            "31  | B   | I IV III   | 16 26 08     | AD CN ET FL GI JV KZ PU QY WX | jkm ogi ncj glp"
+        );
+        assert_eq!(KeySheet::from_text(&text).unwrap(), sheet);
+
+        let generated = KeySheet::generate(MachineModel::EnigmaI, 1944, 10, Some(1)).unwrap();
+        let text = generated.to_text();
+        assert!(text.lines().nth(2).unwrap().starts_with("31"));
+        let mut parsed = KeySheet::from_text(&text).unwrap();
+        parsed.keys.reverse();
+        assert_eq!(parsed, generated);
+    }
+}
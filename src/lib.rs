@@ -1,6 +1,19 @@
+pub mod alphabet;
 pub mod cipher;
 pub mod common;
 pub mod enigma;
+pub mod entry;
+pub mod format;
+pub mod indicator;
+pub mod keysheet;
+pub mod machine;
+pub mod model;
+pub mod naval;
 pub mod plugboard;
 pub mod reflector;
 pub mod rotor;
+pub mod stepping;
+pub mod stream;
+pub mod transliteration;
+pub mod typex;
+pub mod uhr;
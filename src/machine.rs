@@ -0,0 +1,33 @@
+use crate::{
+    alphabet::Alphabet,
+    enigma::{EncodeError, Enigma},
+    typex::Typex,
+};
+
+/// A rotor machine that enciphers text, such as the `Enigma` or the `Typex`
+/// Tools that only need to encipher text can take any `Machine` and drive either of them
+pub trait Machine {
+    /// Enciphers every symbol of the machine's alphabet in `s`. Any other character is handled by the machine's
+    /// `NonAlphabetic` policy, which passes it through unchanged unless another policy was set
+    ///
+    /// Panics if the policy rejects a character or can not replace it, see `Machine::try_encode`
+    fn encode(&self, s: &str) -> String {
+        self.try_encode(s).unwrap()
+    }
+
+    /// Like `encode`, but fails instead of panicking if the `NonAlphabetic` policy rejects a character of `s` or
+    /// can not replace it
+    fn try_encode(&self, s: &str) -> Result<String, EncodeError>;
+}
+
+impl<A: Alphabet> Machine for Enigma<A> {
+    fn try_encode(&self, s: &str) -> Result<String, EncodeError> {
+        Enigma::try_encode(self, s)
+    }
+}
+
+impl Machine for Typex {
+    fn try_encode(&self, s: &str) -> Result<String, EncodeError> {
+        Typex::try_encode(self, s)
+    }
+}
@@ -1,44 +1,174 @@
-use std::fs;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    process,
+};
 
+use anyhow::anyhow;
+use bruh_moment::Bruh;
 use clap::Parser;
 use inquire::Text;
-use interface::{Args, Config};
-use libenigma::enigma::Enigma;
+use interface::{keyring, Args, Config};
+use libenigma::{
+    alphabet::Latin,
+    enigma::Enigma,
+    format::{ungroup, Conventions},
+    indicator::{random_setting, Procedure},
+};
 
 mod interface;
 fn main() {
     let args = Args::parse();
 
-    let config: Config = match args.config {
-        Some(path) => match fs::read_to_string(&path) {
-            Ok(content) => serde_yaml::from_str(&content).unwrap(),
-            Err(_) => {
-                let config = Config::new();
-                let cfg_string = serde_yaml::to_string(&config).unwrap();
-                fs::write(path, cfg_string).unwrap();
-                config
+    let e = match &args.keyring {
+        Some(path) => match keyring(path, args.date.as_deref()) {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
             }
         },
-        None => Config::new(),
+        None => {
+            let config: Config = match &args.config {
+                Some(path) => match fs::read_to_string(path) {
+                    Ok(content) => serde_yaml::from_str(&content).unwrap(),
+                    Err(_) => {
+                        let config = Config::new();
+                        let cfg_string = serde_yaml::to_string(&config).unwrap();
+                        fs::write(path, cfg_string).unwrap();
+                        config
+                    }
+                },
+                None => Config::new(),
+            };
+
+            Enigma::try_from(config).unwrap()
+        }
+    };
+    let e = match args.non_alphabetic {
+        Some(policy) => e.with_non_alphabetic(policy),
+        None => e,
+    };
+    let mut e = match args.transliteration {
+        Some(transliteration) => e.with_transliteration(transliteration),
+        None => e,
     };
 
-    let e = Enigma::try_from(config).unwrap();
+    let formatted =
+        args.grouping.is_some() || args.conventions.is_some() || args.indicator.is_some();
+    let encoded = match &args.input {
+        Some(path) if !formatted => {
+            if let Some(output) = args
+                .output
+                .as_deref()
+                .filter(|output| same_file(path, output))
+            {
+                eprintln!("Recieved {output} as both input and output: It would be emptied before it is read");
+                process::exit(1);
+            }
+            let reader = match File::open(path) {
+                Ok(file) => BufReader::new(file),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+            };
+            match &args.output {
+                Some(path) => match File::create(path) {
+                    Ok(file) => e.encode_stream(reader, BufWriter::new(file)),
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                },
+                None => e.encode_stream(reader, io::stdout().lock()),
+            }
+            .map(|_| ())
+            .map_err(Bruh::from)
+        }
+        input => {
+            let text = match (input, &args.plaintext) {
+                (Some(path), _) => fs::read_to_string(path).map_err(Bruh::from),
+                (None, Some(text)) => Ok(text.clone()),
+                (None, None) => Ok(Text::new("Please enter text to encode:").prompt().unwrap()),
+            };
 
-    let plaintext: String = match args.input {
-        Some(path) => {
-            let buf = fs::read(path).unwrap();
-            String::from_utf8(buf).unwrap()
+            text.and_then(|text| encipher(&mut e, &args, text))
+                .and_then(|text| match &args.output {
+                    Some(path) => fs::write(path, text).map_err(Bruh::from),
+                    None => {
+                        println!("{text}");
+                        Ok(())
+                    }
+                })
         }
-        None => match args.plaintext {
-            Some(text) => text,
-            None => Text::new("Please enter text to encode:").prompt().unwrap(),
-        },
     };
 
-    let ciphertext = e.encode(&plaintext);
+    if let Err(e) = encoded {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
 
-    match args.output {
-        Some(path) => fs::write(path, ciphertext).unwrap(),
-        None => println!("{ciphertext}"),
+/// Whether `input` and `output` are the same file, which streaming would truncate before reading
+fn same_file(input: &str, output: &str) -> bool {
+    match (fs::canonicalize(input), fs::canonicalize(output)) {
+        (Ok(input), Ok(output)) => input == output,
+        _ => false,
     }
 }
+
+/// Enciphers or deciphers `text` with the formatting, conventions and indicator procedure given on the command line
+fn encipher(e: &mut Enigma, args: &Args, text: String) -> Result<String, Bruh> {
+    let conventions = args
+        .conventions
+        .map(|numbers| Conventions::new(numbers).with_restored(args.restore.iter().copied()));
+    let ground = args.ground.as_deref();
+
+    if args.decrypt {
+        let text = match args.grouping {
+            Some(_) => ungroup(&text),
+            None => text,
+        };
+        let plaintext = match args.indicator {
+            Some(procedure) => procedure.decipher(e, ground, &text)?.1,
+            None => e.try_encode(&text)?,
+        };
+        return Ok(match conventions {
+            Some(conventions) => conventions.restore(&plaintext),
+            None => plaintext,
+        });
+    }
+
+    let text = match conventions {
+        Some(conventions) => conventions.prepare(&text),
+        None => text,
+    };
+    let (indicator, ciphertext) = match args.indicator {
+        Some(procedure) => {
+            let width = e.window().chars().count();
+            let setting = match (procedure, ground) {
+                (_, Some(ground)) => ground.to_string(),
+                (Procedure::Doubled, None) => {
+                    return Err(anyhow!("The doubled indicator needs the ground setting"))
+                }
+                (Procedure::Chosen, None) => random_setting::<Latin>(width),
+            };
+            let message_key = args
+                .message_key
+                .clone()
+                .unwrap_or_else(|| random_setting::<Latin>(width));
+            let (indicator, ciphertext) = procedure.encipher(e, &setting, &message_key, &text)?;
+            (Some(indicator), ciphertext)
+        }
+        None => (None, e.try_encode(&text)?),
+    };
+    let ciphertext = match args.grouping {
+        Some(grouping) => grouping.group(&ciphertext, args.groups_per_line),
+        None => ciphertext,
+    };
+    Ok(match indicator {
+        Some(indicator) => format!("{indicator}\n{ciphertext}"),
+        None => ciphertext,
+    })
+}
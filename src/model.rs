@@ -0,0 +1,462 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+use thiserror::Error;
+
+use crate::{
+    common::Position,
+    enigma::Enigma,
+    entry::EntryWheels,
+    plugboard::{Stecker, MAX_PLUGS},
+    reflector::{ReflectorKind, Reflectors},
+    rotor::{RotorKind, Rotors},
+    stepping::Drive,
+};
+
+/// This enum represents each machine model that can be simulated
+/// A model decides which rotors, reflectors and entry wheel fit into the machine, and whether it has a plugboard
+#[derive(
+    EnumString, EnumIter, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash,
+)]
+pub enum MachineModel {
+    /// Army and Air Force Enigma I
+    EnigmaI,
+    /// Navy M3
+    M3,
+    /// Four rotor Navy M4
+    M4,
+    /// Postwar Norwegian Enigma I with rewired rotors
+    Norway,
+    /// Commercial Enigma D
+    D,
+    /// Commercial Enigma K
+    K,
+    /// Abwehr Enigma G-312, with cog wheels and a moving reflector
+    G,
+    /// Enigma T (Tirpitz), built for the Japanese Navy
+    T,
+    /// Reichsbahn "Rocket" Enigma
+    Railway,
+    /// Enigma K with the rotors rewired by the Swiss army
+    SwissK,
+}
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("Recieved {1} rotors, The {0} takes {2} rotors")]
+    RotorCount(MachineModel, usize, usize),
+    #[error("Rotor {1} does not fit in position {2} of the {0}")]
+    Rotor(MachineModel, RotorKind, usize),
+    #[error("Reflector {1} does not fit in the {0}")]
+    Reflector(MachineModel, ReflectorKind),
+    #[error("The {0} uses the {1} entry wheel")]
+    EntryWheel(MachineModel, EntryWheels),
+    #[error("The {0} has no plugboard")]
+    NoPlugboard(MachineModel),
+    #[error("The Uhr does not fit the {0}")]
+    Uhr(MachineModel),
+    #[error("Recieved {1} plugs, No more than {2} plugs fit in the plugboard of the {0}")]
+    TooManyPlugs(MachineModel, usize, usize),
+    #[error("The reflector of the {0} can not be set")]
+    ReflectorPosition(MachineModel),
+    #[error("The reflector of the {0} does not step")]
+    SteppingReflector(MachineModel),
+    #[error("The reflector of the {0} steps with the rotors")]
+    FixedReflector(MachineModel),
+    #[error("The rotors of the {0} are moved by {1}")]
+    Drive(MachineModel, Drive),
+}
+
+impl MachineModel {
+    /// Rotors that fit in the three moving positions
+    pub fn rotors(&self) -> &'static [Rotors] {
+        use Rotors::*;
+        match self {
+            MachineModel::EnigmaI => &[I, II, III, IV, V],
+            MachineModel::M3 | MachineModel::M4 => &[I, II, III, IV, V, VI, VII, VIII],
+            MachineModel::Norway => &[NorwayI, NorwayII, NorwayIII, NorwayIV, NorwayV],
+            MachineModel::D | MachineModel::K => &[KI, KII, KIII],
+            MachineModel::G => &[GI, GII, GIII],
+            MachineModel::T => &[TI, TII, TIII, TIV, TV, TVI, TVII, TVIII],
+            MachineModel::Railway => &[RailwayI, RailwayII, RailwayIII],
+            MachineModel::SwissK => &[SwissKI, SwissKII, SwissKIII],
+        }
+    }
+
+    /// Greek wheels that fit in the fourth position, empty for three rotor machines
+    pub fn greek_wheels(&self) -> &'static [Rotors] {
+        match self {
+            MachineModel::M4 => &[Rotors::Beta, Rotors::Gamma],
+            _ => &[],
+        }
+    }
+
+    /// Number of rotors in the machine, including the Greek wheel
+    pub fn rotor_count(&self) -> usize {
+        match self.greek_wheels().is_empty() {
+            true => 3,
+            false => 4,
+        }
+    }
+
+    /// Fixed reflectors that fit in the machine
+    pub fn reflectors(&self) -> &'static [Reflectors] {
+        use Reflectors::*;
+        match self {
+            MachineModel::EnigmaI => &[A, B, C],
+            MachineModel::M3 => &[B, C],
+            MachineModel::M4 => &[BThin, CThin],
+            MachineModel::Norway => &[Norway],
+            MachineModel::D | MachineModel::K | MachineModel::SwissK => &[K],
+            MachineModel::G => &[G],
+            MachineModel::T => &[T],
+            MachineModel::Railway => &[Railway],
+        }
+    }
+
+    /// Whether the rewirable UKW-D fits in the machine
+    pub fn ukw_d(&self) -> bool {
+        matches!(self, MachineModel::EnigmaI)
+    }
+
+    pub fn entry_wheel(&self) -> EntryWheels {
+        match self {
+            MachineModel::T => EntryWheels::Tirpitz,
+            _ if self.military() => EntryWheels::Alphabetical,
+            _ => EntryWheels::Qwertz,
+        }
+    }
+
+    /// Most plugs the plugboard takes, `None` if the machine has no plugboard
+    /// The Army and Air Force could plug every cable handed out with the Enigma I, the Navy and the postwar
+    /// Norwegian key lists never plugged more than 10
+    pub fn max_plugs(&self) -> Option<usize> {
+        match self {
+            MachineModel::EnigmaI => Some(MAX_PLUGS),
+            MachineModel::M3 | MachineModel::M4 | MachineModel::Norway => Some(10),
+            _ => None,
+        }
+    }
+
+    /// Whether the Uhr switch box can be plugged in instead of plug cables
+    pub fn uhr(&self) -> bool {
+        matches!(self, MachineModel::EnigmaI)
+    }
+
+    /// Whether the operator can set the reflector to any position
+    pub fn settable_reflector(&self) -> bool {
+        !self.military()
+    }
+
+    /// Whether the reflector moves with the rotors
+    pub fn stepping_reflector(&self) -> bool {
+        matches!(self, MachineModel::G)
+    }
+
+    pub fn drive(&self) -> Drive {
+        match self {
+            MachineModel::G => Drive::CogWheels,
+            _ => Drive::Pawls,
+        }
+    }
+
+    /// The Enigma I and the machines derived from it, which have a plugboard and a fixed reflector
+    fn military(&self) -> bool {
+        matches!(
+            self,
+            MachineModel::EnigmaI | MachineModel::M3 | MachineModel::M4 | MachineModel::Norway
+        )
+    }
+
+    fn fits(&self, rotor: &RotorKind, i: usize) -> bool {
+        let allowed = match i {
+            3 => self.greek_wheels(),
+            _ => self.rotors(),
+        };
+        match rotor {
+            RotorKind::Builtin(r) => allowed.contains(r),
+            RotorKind::Custom(_) | RotorKind::Z(_) => false,
+        }
+    }
+
+    /// Checks that every component of `enigma` could have been fitted to this model
+    pub fn check(&self, enigma: &Enigma) -> Result<(), ModelError> {
+        let rotors = enigma.rotors();
+        let count = rotors.kinds().count();
+        if count != self.rotor_count() {
+            return Err(ModelError::RotorCount(*self, count, self.rotor_count()));
+        }
+        if let Some((i, r)) = rotors.kinds().enumerate().find(|(i, r)| !self.fits(r, *i)) {
+            return Err(ModelError::Rotor(*self, r.clone(), i + 1));
+        }
+
+        let reflector = enigma.reflector();
+        let fits = match reflector.kind() {
+            ReflectorKind::Builtin(r) => self.reflectors().contains(&r),
+            ReflectorKind::UkwD => self.ukw_d(),
+            ReflectorKind::Z | ReflectorKind::Custom => false,
+        };
+        if !fits {
+            return Err(ModelError::Reflector(*self, reflector.kind()));
+        }
+        if !self.settable_reflector() && reflector.position() != Position::default() {
+            return Err(ModelError::ReflectorPosition(*self));
+        }
+        match (rotors.drives_reflector(), self.stepping_reflector()) {
+            (true, false) => return Err(ModelError::SteppingReflector(*self)),
+            (false, true) => return Err(ModelError::FixedReflector(*self)),
+            _ => (),
+        }
+        if rotors.drive() != Some(self.drive()) {
+            return Err(ModelError::Drive(*self, self.drive()));
+        }
+
+        if enigma.entry().kind() != Some(self.entry_wheel()) {
+            return Err(ModelError::EntryWheel(*self, self.entry_wheel()));
+        }
+
+        // A missing plugboard is the same as one without plug cables
+        let plugs = match enigma.plugboard() {
+            None => return Ok(()),
+            Some(Stecker::Plugboard(plugboard)) => plugboard.plugs(),
+            Some(Stecker::Uhr(_)) if self.uhr() => return Ok(()),
+            Some(Stecker::Uhr(_)) => return Err(ModelError::Uhr(*self)),
+        };
+        match self.max_plugs() {
+            None => Err(ModelError::NoPlugboard(*self)),
+            Some(max) if plugs > max => Err(ModelError::TooManyPlugs(*self, plugs, max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MachineModel, ModelError};
+    use crate::{
+        common::Character,
+        enigma::Enigma,
+        entry::EntryWheels,
+        plugboard::{Plug, Plugboard, Plugs, Stecker},
+        reflector::{Reflector, Reflectors},
+        rotor::{CustomRotor, RotorConfig, RotorKind, Rotors},
+        stepping::Drive,
+        uhr::Uhr,
+    };
+    use itertools::Itertools;
+    use strum::IntoEnumIterator;
+
+    fn plugboard(pairs: &str) -> Option<Stecker> {
+        let plugs: Vec<Plug> = pairs
+            .chars()
+            .map(|c| Character::try_from(c).unwrap())
+            .tuples()
+            .map(|p: (Character, Character)| Plug::try_from(p).unwrap())
+            .collect();
+        match plugs.is_empty() {
+            true => None,
+            false => Some(
+                Plugboard::try_from(Plugs::with_limit(plugs, 13).unwrap())
+                    .unwrap()
+                    .into(),
+            ),
+        }
+    }
+
+    fn rotors(model: MachineModel) -> RotorConfig {
+        let mut rotors = model.rotors()[..3]
+            .iter()
+            .map(|r| (RotorKind::from(*r), 'A', 'A'))
+            .collect_vec();
+        rotors.extend(
+            model
+                .greek_wheels()
+                .iter()
+                .take(1)
+                .map(|r| (RotorKind::from(*r), 'A', 'A')),
+        );
+        RotorConfig::try_from(rotors).unwrap()
+    }
+
+    #[test]
+    fn every_model() {
+        MachineModel::iter().for_each(|model| {
+            let plugs = match model.max_plugs() {
+                Some(max) => &"ABCDEFGHIJKLMNOPQRSTUVWXYZ"[..2 * max],
+                None => "",
+            };
+            Enigma::for_model(
+                model,
+                rotors(model),
+                plugboard(plugs),
+                model.reflectors()[0],
+            )
+            .unwrap();
+        })
+    }
+
+    #[test]
+    fn wrong_rotor() {
+        let rotors =
+            RotorConfig::try_from([(Rotors::VI, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        let e = Enigma::for_model(MachineModel::EnigmaI, rotors, plugboard(""), Reflectors::B);
+        assert!(matches!(e, Err(ModelError::Rotor(_, _, 1))));
+    }
+
+    #[test]
+    fn custom_rotor() {
+        let custom = CustomRotor::new("EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q", None).unwrap();
+        let rotors = RotorConfig::try_from(vec![
+            (RotorKind::from(Rotors::I), 'A', 'A'),
+            (RotorKind::from(Rotors::II), 'A', 'A'),
+            (RotorKind::from(custom), 'A', 'A'),
+        ])
+        .unwrap();
+        let e = Enigma::for_model(MachineModel::M3, rotors, plugboard(""), Reflectors::B);
+        assert!(matches!(e, Err(ModelError::Rotor(_, _, 3))));
+    }
+
+    #[test]
+    fn rotor_count() {
+        let e = Enigma::for_model(
+            MachineModel::M4,
+            rotors(MachineModel::M3),
+            plugboard(""),
+            Reflectors::BThin,
+        );
+        assert!(matches!(e, Err(ModelError::RotorCount(_, 3, 4))));
+    }
+
+    #[test]
+    fn wrong_reflector() {
+        let e = Enigma::for_model(
+            MachineModel::M3,
+            rotors(MachineModel::M3),
+            plugboard(""),
+            Reflectors::A,
+        );
+        assert!(matches!(e, Err(ModelError::Reflector(..))));
+
+        let pairs = "ACBDEFGHIKLMNOPQRSTUVWXZ"
+            .chars()
+            .map(|c| Character::try_from(c).unwrap())
+            .tuples()
+            .collect_vec();
+        Enigma::for_model(
+            MachineModel::EnigmaI,
+            rotors(MachineModel::EnigmaI),
+            plugboard(""),
+            Reflector::ukw_d(&pairs).unwrap(),
+        )
+        .unwrap();
+        let e = Enigma::for_model(
+            MachineModel::M3,
+            rotors(MachineModel::M3),
+            plugboard(""),
+            Reflector::ukw_d(&pairs).unwrap(),
+        );
+        assert!(matches!(e, Err(ModelError::Reflector(..))));
+    }
+
+    #[test]
+    fn reflector_position() {
+        let reflector = Reflector::from(Reflectors::B).with_position('C').unwrap();
+        let e = Enigma::for_model(
+            MachineModel::EnigmaI,
+            rotors(MachineModel::EnigmaI),
+            plugboard(""),
+            reflector,
+        );
+        assert!(matches!(e, Err(ModelError::ReflectorPosition(_))));
+
+        let reflector = Reflector::from(Reflectors::K).with_position('C').unwrap();
+        Enigma::for_model(
+            MachineModel::K,
+            rotors(MachineModel::K),
+            plugboard(""),
+            reflector,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn plugboard_presence() {
+        let e = Enigma::for_model(
+            MachineModel::D,
+            rotors(MachineModel::D),
+            plugboard("AB"),
+            Reflectors::K,
+        );
+        assert!(matches!(e, Err(ModelError::NoPlugboard(_))));
+    }
+
+    #[test]
+    fn too_many_plugs() {
+        // One plug more than the model takes
+        MachineModel::iter()
+            .filter_map(|model| Some((model, model.max_plugs()?)))
+            .filter(|(_, max)| *max < 13)
+            .for_each(|(model, max)| {
+                let e = Enigma::for_model(
+                    model,
+                    rotors(model),
+                    plugboard(&"ABCDEFGHIJKLMNOPQRSTUVWXYZ"[..2 * (max + 1)]),
+                    model.reflectors()[0],
+                );
+                assert!(
+                    matches!(e, Err(ModelError::TooManyPlugs(_, n, m)) if n == max + 1 && m == max)
+                );
+            })
+    }
+
+    #[test]
+    fn stepping() {
+        let e = Enigma::without_plugboard(rotors(MachineModel::G), Reflectors::G)
+            .with_entry_wheel(EntryWheels::Qwertz);
+        assert!(matches!(
+            MachineModel::G.check(&e),
+            Err(ModelError::FixedReflector(_))
+        ));
+        let e = e.with_stepping_reflector();
+        assert!(matches!(
+            MachineModel::G.check(&e),
+            Err(ModelError::Drive(_, Drive::CogWheels))
+        ));
+    }
+
+    #[test]
+    fn uhr() {
+        let pairs = "AVBSCGDLFUHZINKMOWRX"
+            .chars()
+            .map(|c| Character::try_from(c).unwrap())
+            .tuples()
+            .collect_vec();
+        let uhr = || Some(Uhr::new(&pairs, 12).unwrap().into());
+
+        Enigma::for_model(
+            MachineModel::EnigmaI,
+            rotors(MachineModel::EnigmaI),
+            uhr(),
+            Reflectors::B,
+        )
+        .unwrap();
+        let e = Enigma::for_model(
+            MachineModel::M3,
+            rotors(MachineModel::M3),
+            uhr(),
+            Reflectors::B,
+        );
+        assert!(matches!(e, Err(ModelError::Uhr(_))));
+    }
+
+    #[test]
+    fn entry_wheel() {
+        let e = Enigma::without_plugboard(rotors(MachineModel::K), Reflectors::K);
+        assert!(matches!(
+            MachineModel::K.check(&e),
+            Err(ModelError::EntryWheel(_, EntryWheels::Qwertz))
+        ));
+        let e = e.with_entry_wheel(EntryWheels::Qwertz);
+        assert!(MachineModel::K.check(&e).is_ok());
+    }
+}
@@ -0,0 +1,361 @@
+use std::fmt::{Display, Formatter};
+
+use bruh_moment::Bruh;
+use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    alphabet::Latin, common::Character, enigma::Enigma, format::ungroup, indicator::random_setting,
+};
+
+/// Number of letters in a bigram table, which swaps every pair of them
+const LETTERS: usize = 26;
+
+/// A bigram substitution table (Doppelbuchstabentauschtafel) of the Kriegsmarine
+/// Every pair of letters is swapped with another pair, which is swapped back, so the same table hides and reveals an
+/// indicator. A table is written as the 338 swapped pairs of bigrams
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "Vec<(String, String)>", into = "Vec<(String, String)>")]
+pub struct BigramTable {
+    swaps: Vec<usize>,
+}
+
+/// The two groups the operator took from the Kenngruppenbuch for a message
+/// The Kenngruppenbuch and the bigram tables are written in the 26 letters, so the naval procedure only works with
+/// machines that encipher `Latin`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Kenngruppen {
+    /// The Schlüsselkenngruppe tells the receiver which key the message was enciphered with
+    key: String,
+    /// The Verfahrenkenngruppe is enciphered at the ground setting to give the message key
+    procedure: String,
+}
+
+/// The two four letter groups sent at the start and repeated at the end of a naval message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NavalIndicator([String; 2]);
+
+#[derive(Error, Debug)]
+pub enum NavalError {
+    #[error("Recieved {0}, a bigram is two letters")]
+    Bigram(String),
+    #[error("Bigram {0} must be swapped with exactly one other bigram")]
+    NotInvolution(String),
+    #[error("Recieved {0}, Kenngruppen are three letters")]
+    Trigram(String),
+    #[error("Recieved a message of {0} letters, the indicator alone takes 8")]
+    Short(usize),
+    #[error("The naval procedure needs a machine with at least three rotors")]
+    Width,
+}
+
+impl BigramTable {
+    /// A table with every bigram swapped with a randomly chosen other bigram. The same `seed` always gives the same
+    /// table
+    pub fn random(seed: Option<u64>) -> BigramTable {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut bigrams: Vec<usize> = (0..LETTERS * LETTERS).collect();
+        bigrams.shuffle(&mut rng);
+
+        let mut swaps = vec![0; LETTERS * LETTERS];
+        bigrams.chunks(2).for_each(|pair| {
+            swaps[pair[0]] = pair[1];
+            swaps[pair[1]] = pair[0];
+        });
+        BigramTable { swaps }
+    }
+
+    /// Swaps the bigram `(l, r)`
+    pub fn substitute(&self, l: Character, r: Character) -> (Character, Character) {
+        let swapped = self.swaps[index(l, r)];
+        (letter(swapped / LETTERS), letter(swapped % LETTERS))
+    }
+
+    /// Every pair of swapped bigrams, each pair listed once
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        self.swaps
+            .iter()
+            .enumerate()
+            .filter(|(from, to)| from < to)
+            .map(|(from, to)| (bigram(from), bigram(*to)))
+            .collect()
+    }
+}
+
+impl TryFrom<Vec<(String, String)>> for BigramTable {
+    type Error = NavalError;
+
+    fn try_from(value: Vec<(String, String)>) -> Result<Self, Self::Error> {
+        let mut swaps: Vec<Option<usize>> = vec![None; LETTERS * LETTERS];
+        for (l, r) in value.iter() {
+            let (l, r) = (parse_bigram(l)?, parse_bigram(r)?);
+            for (from, to) in [(l, r), (r, l)] {
+                if swaps[from].is_some() || from == to {
+                    return Err(NavalError::NotInvolution(bigram(from)));
+                }
+                swaps[from] = Some(to);
+            }
+        }
+
+        let swaps = swaps
+            .iter()
+            .enumerate()
+            .map(|(i, to)| to.ok_or_else(|| NavalError::NotInvolution(bigram(i))))
+            .try_collect()?;
+        Ok(BigramTable { swaps })
+    }
+}
+
+impl From<BigramTable> for Vec<(String, String)> {
+    fn from(value: BigramTable) -> Self {
+        value.pairs()
+    }
+}
+
+impl Kenngruppen {
+    /// Fails unless both groups are three letters. Lowercase letters are written in capitals
+    pub fn new(key: &str, procedure: &str) -> Result<Kenngruppen, NavalError> {
+        let (key, procedure) = (key.to_ascii_uppercase(), procedure.to_ascii_uppercase());
+        for group in [&key, &procedure] {
+            if group.chars().count() != 3
+                || group
+                    .chars()
+                    .any(|c| Character::<Latin>::try_from(c).is_err())
+            {
+                return Err(NavalError::Trigram(group.to_string()));
+            }
+        }
+        Ok(Kenngruppen { key, procedure })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn procedure(&self) -> &str {
+        &self.procedure
+    }
+
+    /// Enciphers the Verfahrenkenngruppe at `ground` into the message key. On a machine with a Greek wheel the slow
+    /// parts keep their letters from the ground setting. Leaves the machine turned to `ground`
+    /// The machine is a `Latin` one, since the Verfahrenkenngruppe is typed on it as it stands in the Kenngruppenbuch
+    pub fn message_key(&self, enigma: &mut Enigma, ground: &str) -> Result<String, Bruh> {
+        let width = enigma.window().chars().count();
+        if width < 3 {
+            return Err(NavalError::Width.into());
+        }
+
+        enigma.set_positions(ground)?;
+        let slow: String = ground.chars().take(width - 3).collect();
+        Ok(slow + &enigma.try_encode(&self.procedure)?)
+    }
+}
+
+impl NavalIndicator {
+    /// Writes the Schlüsselkenngruppe behind the first filler and the Verfahrenkenngruppe in front of the second one
+    /// in two rows, and swaps the bigrams read down the columns with `table`
+    pub fn encode(
+        table: &BigramTable,
+        kenngruppen: &Kenngruppen,
+        fillers: [char; 2],
+    ) -> Result<NavalIndicator, Bruh> {
+        let top = format!("{}{}", fillers[0], kenngruppen.key);
+        let bottom = format!("{}{}", kenngruppen.procedure, fillers[1]);
+        Ok(NavalIndicator(swap_columns(table, &top, &bottom)?))
+    }
+
+    /// Swaps the bigrams back with `table` and reads the Kenngruppen out of the rows
+    pub fn decode(&self, table: &BigramTable) -> Result<Kenngruppen, Bruh> {
+        let [top, bottom] = swap_columns(table, &self.0[0], &self.0[1])?;
+        Ok(Kenngruppen::new(&top[1..], &bottom[..3])?)
+    }
+
+    /// Splits the indicator off the start of `message`, which may be written in groups, and drops its repetition at
+    /// the end of the message. Returns the indicator and the body without the spaces between groups
+    pub fn parse(message: &str) -> Result<(NavalIndicator, String), NavalError> {
+        let letters: Vec<char> = ungroup(message).chars().collect();
+        if letters.len() < 8 {
+            return Err(NavalError::Short(letters.len()));
+        }
+
+        let (header, body) = letters.split_at(8);
+        let body = match body.ends_with(header) {
+            true => &body[..body.len() - 8],
+            false => body,
+        };
+        let indicator =
+            NavalIndicator([header[..4].iter().collect(), header[4..].iter().collect()]);
+        Ok((indicator, body.iter().collect()))
+    }
+
+    /// Enciphers `plaintext` with the message key given by the Kenngruppen and the ground setting, and returns it with
+    /// the indicator hiding the Kenngruppen. Leaves the machine turned to the message key
+    pub fn encipher(
+        enigma: &mut Enigma,
+        table: &BigramTable,
+        ground: &str,
+        kenngruppen: &Kenngruppen,
+        plaintext: &str,
+    ) -> Result<(NavalIndicator, String), Bruh> {
+        let fillers: Vec<char> = random_setting::<Latin>(2).chars().collect();
+        let indicator = NavalIndicator::encode(table, kenngruppen, [fillers[0], fillers[1]])?;
+        let message_key = kenngruppen.message_key(enigma, ground)?;
+        enigma.set_positions(&message_key)?;
+        Ok((indicator, enigma.try_encode(plaintext)?))
+    }
+
+    /// Reads the indicator of an intercepted `message`, recovers the Kenngruppen and the message key, and deciphers
+    /// the body. Leaves the machine turned to the message key
+    pub fn decipher(
+        enigma: &mut Enigma,
+        table: &BigramTable,
+        ground: &str,
+        message: &str,
+    ) -> Result<(Kenngruppen, String), Bruh> {
+        let (indicator, body) = NavalIndicator::parse(message)?;
+        let kenngruppen = indicator.decode(table)?;
+        let message_key = kenngruppen.message_key(enigma, ground)?;
+        enigma.set_positions(&message_key)?;
+        let plaintext = enigma.try_encode(&body)?;
+        Ok((kenngruppen, plaintext))
+    }
+}
+
+impl Display for NavalIndicator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.0[0], self.0[1])
+    }
+}
+
+/// Swaps the bigram in each column of the two rows, and returns the rows
+fn swap_columns(table: &BigramTable, top: &str, bottom: &str) -> Result<[String; 2], Bruh> {
+    let mut rows = [String::new(), String::new()];
+    for (t, b) in top.chars().zip(bottom.chars()) {
+        let (t, b) = table.substitute(Character::try_from(t)?, Character::try_from(b)?);
+        rows[0].push(t.into());
+        rows[1].push(b.into());
+    }
+    Ok(rows)
+}
+
+fn index(l: Character, r: Character) -> usize {
+    l.get_offset() as usize * LETTERS + r.get_offset() as usize
+}
+
+fn letter(offset: usize) -> Character {
+    Character::try_from(char::from(b'A' + offset as u8)).unwrap()
+}
+
+fn bigram(i: usize) -> String {
+    format!("{}{}", letter(i / LETTERS), letter(i % LETTERS))
+}
+
+fn parse_bigram(s: &str) -> Result<usize, NavalError> {
+    let letters: Vec<Character> = s
+        .chars()
+        .map(Character::try_from)
+        .try_collect()
+        .map_err(|_| NavalError::Bigram(s.to_string()))?;
+    match letters[..] {
+        [l, r] => Ok(index(l, r)),
+        _ => Err(NavalError::Bigram(s.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::Character,
+        enigma::Enigma,
+        reflector::Reflectors,
+        rotor::{RotorConfig, Rotors},
+    };
+
+    use super::{BigramTable, Kenngruppen, NavalIndicator};
+
+    fn construct_m4() -> Enigma {
+        let rotors = RotorConfig::try_from([
+            (Rotors::III, 'A'),
+            (Rotors::II, 'A'),
+            (Rotors::I, 'A'),
+            (Rotors::Beta, 'A'),
+        ])
+        .unwrap();
+        Enigma::without_plugboard(rotors, Reflectors::BThin)
+    }
+
+    #[test]
+    fn random_table() {
+        let table = BigramTable::random(Some(1937));
+        let a = Character::try_from('A').unwrap();
+        let b = Character::try_from('B').unwrap();
+
+        let (l, r) = table.substitute(a, b);
+        assert_ne!((l, r), (a, b));
+        assert_eq!(table.substitute(l, r), (a, b));
+        assert_eq!(table, BigramTable::random(Some(1937)));
+        assert_eq!(table.pairs().len(), 338);
+    }
+
+    #[test]
+    fn table_from_pairs() {
+        let table = BigramTable::random(None);
+        assert_eq!(BigramTable::try_from(table.pairs()).unwrap(), table);
+
+        let mut pairs = table.pairs();
+        pairs.pop();
+        assert!(BigramTable::try_from(pairs.clone()).is_err());
+        pairs.push(("AA".to_string(), "AA".to_string()));
+        assert!(BigramTable::try_from(pairs).is_err());
+        assert!(BigramTable::try_from(vec![("ABC".to_string(), "DE".to_string())]).is_err());
+    }
+
+    #[test]
+    fn serde() {
+        let table = BigramTable::random(Some(1));
+        let yaml = serde_yaml::to_string(&table).unwrap();
+
+        assert_eq!(serde_yaml::from_str::<BigramTable>(&yaml).unwrap(), table);
+    }
+
+    #[test]
+    fn indicator() {
+        let table = BigramTable::random(Some(42));
+        let kenngruppen = Kenngruppen::new("SWQ", "RAF").unwrap();
+        assert_eq!(kenngruppen.key(), "SWQ");
+        assert_eq!(kenngruppen.procedure(), "RAF");
+
+        let indicator = NavalIndicator::encode(&table, &kenngruppen, ['X', 'Y']).unwrap();
+        assert_eq!(indicator.decode(&table).unwrap(), kenngruppen);
+        assert!(Kenngruppen::new("SW", "RAF").is_err());
+        assert!(Kenngruppen::new("SWQ", "RA1").is_err());
+        assert_eq!(Kenngruppen::new("swq", "raf").unwrap(), kenngruppen);
+    }
+
+    #[test]
+    fn round_trip() {
+        let table = BigramTable::random(Some(42));
+        let kenngruppen = Kenngruppen::new("SWQ", "RAF").unwrap();
+        let mut e = construct_m4();
+
+        let (indicator, ciphertext) =
+            NavalIndicator::encipher(&mut e, &table, "ZABC", &kenngruppen, "UBOOTSICHTET").unwrap();
+        // The Greek wheel keeps its letter from the ground setting
+        assert!(kenngruppen
+            .message_key(&mut e, "ZABC")
+            .unwrap()
+            .starts_with('Z'));
+
+        let message = format!("{indicator} {ciphertext} {indicator}");
+        let (received, plaintext) =
+            NavalIndicator::decipher(&mut e, &table, "ZABC", &message).unwrap();
+        assert_eq!(received, kenngruppen);
+        assert_eq!(plaintext, "UBOOTSICHTET");
+        assert!(NavalIndicator::parse("ABCD").is_err());
+    }
+}
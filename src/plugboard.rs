@@ -1,25 +1,37 @@
 use crate::{
+    alphabet::{Alphabet, Latin},
     cipher::{Cipher, Decode, Encode},
     common::Character,
+    uhr::Uhr,
 };
 use anyhow::{Context, Error};
 use itertools::Itertools;
 use std::collections::HashMap;
 use thiserror::Error;
 
-pub struct Plugboard {
-    cipher: Cipher,
+pub struct Plugboard<A: Alphabet = Latin> {
+    cipher: Cipher<A>,
+    plugs: usize,
+}
+
+/// What is plugged into the sockets of the plugboard, either plug cables or the Uhr switch box
+pub enum Stecker<A: Alphabet = Latin> {
+    Plugboard(Plugboard<A>),
+    Uhr(Uhr<A>),
 }
 
 #[derive(Debug)]
-pub struct Plugs(Vec<Plug>);
+pub struct Plugs<A: Alphabet = Latin>(Vec<Plug<A>>);
 
 #[derive(Debug)]
-pub struct Plug(Character, Character);
+pub struct Plug<A: Alphabet = Latin>(Character<A>, Character<A>);
+
+/// Plugs handed out with the Enigma. Key sheets only ever used 10 of them, which is the limit of `Plugs::try_from`
+pub const MAX_PLUGS: usize = 13;
 
 #[derive(Error, Debug)]
 pub enum PlugboardError {
-    #[error("Recieved {0} plugs, No more than 10 plugs may be used in the plugboard")]
+    #[error("Recieved {0} plugs, more than may be used in the plugboard")]
     TooMany(usize),
     #[error("Can not map multiple plugs to the same character")]
     Mapping,
@@ -27,17 +39,15 @@ pub enum PlugboardError {
     Duplicate,
 }
 
-impl TryFrom<Plugs> for Plugboard {
+impl<A: Alphabet> TryFrom<Plugs<A>> for Plugboard<A> {
     type Error = Error;
-    fn try_from(value: Plugs) -> Result<Plugboard, self::Error> {
+    fn try_from(value: Plugs<A>) -> Result<Plugboard<A>, self::Error> {
         // not my best work
-        let passthrough = "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
-            .chars()
-            .map(|c| Character::try_from(c).unwrap());
+        let passthrough = Character::<A>::all();
 
-        let alphabet: HashMap<Character, Character> = passthrough.clone().fold(
+        let alphabet: HashMap<Character<A>, Character<A>> = passthrough.clone().fold(
             HashMap::new(),
-            |mut acc: HashMap<Character, Character>, next| {
+            |mut acc: HashMap<Character<A>, Character<A>>, next| {
                 acc.insert(next, next);
                 acc
             },
@@ -45,14 +55,14 @@ impl TryFrom<Plugs> for Plugboard {
 
         let cipher = value.0.iter().fold(
             alphabet,
-            |mut acc: HashMap<Character, Character>, next: &Plug| {
+            |mut acc: HashMap<Character<A>, Character<A>>, next: &Plug<A>| {
                 acc.insert(next.0, next.1);
                 acc.insert(next.1, next.0);
                 acc
             },
         );
 
-        let x: Vec<Character> = passthrough
+        let x: Vec<Character<A>> = passthrough
             .fold(Vec::new(), |mut acc, next| {
                 let substitution = cipher.get(&next).unwrap();
                 acc.push(substitution);
@@ -62,16 +72,26 @@ impl TryFrom<Plugs> for Plugboard {
             .cloned()
             .collect();
 
-        let cipher: Cipher = Cipher::try_from(x)
-            .with_context(|| format!("Tried to construct a cipher for a plugboard"))?;
-        Ok(Plugboard { cipher: cipher })
+        let cipher: Cipher<A> =
+            Cipher::try_from(x).context("Tried to construct a cipher for a plugboard")?;
+        Ok(Plugboard {
+            cipher,
+            plugs: value.0.len(),
+        })
+    }
+}
+
+impl<A: Alphabet> Plugboard<A> {
+    /// Number of plugs in the plugboard
+    pub fn plugs(&self) -> usize {
+        self.plugs
     }
 }
 
-impl TryFrom<(Character, Character)> for Plug {
+impl<A: Alphabet> TryFrom<(Character<A>, Character<A>)> for Plug<A> {
     type Error = PlugboardError;
 
-    fn try_from(value: (Character, Character)) -> Result<Self, Self::Error> {
+    fn try_from(value: (Character<A>, Character<A>)) -> Result<Self, Self::Error> {
         match value.0 == value.1 {
             true => Err(PlugboardError::Duplicate),
             false => Ok(Plug(value.0, value.1)),
@@ -79,19 +99,27 @@ impl TryFrom<(Character, Character)> for Plug {
     }
 }
 
-impl TryFrom<Vec<Plug>> for Plugs {
+impl<A: Alphabet> TryFrom<Vec<Plug<A>>> for Plugs<A> {
     type Error = PlugboardError;
 
-    fn try_from(value: Vec<Plug>) -> Result<Self, Self::Error> {
+    fn try_from(value: Vec<Plug<A>>) -> Result<Self, Self::Error> {
+        Plugs::with_limit(value, 10)
+    }
+}
+
+impl<A: Alphabet> Plugs<A> {
+    /// Like `Plugs::try_from`, but allows up to `limit` plugs instead of 10, see `MachineModel::max_plugs`
+    /// `PlugboardError::TooMany` does not carry the limit, so callers that take it from a model say so themselves
+    pub fn with_limit(value: Vec<Plug<A>>, limit: usize) -> Result<Self, PlugboardError> {
         let len = value.len();
-        match len {
-            0..=10 => (),
-            _ => return Err(PlugboardError::TooMany(value.len())),
+        let limit = limit.min(MAX_PLUGS);
+        if len > limit {
+            return Err(PlugboardError::TooMany(len));
         }
 
         let uniquechars = value
             .iter()
-            .fold(Vec::new(), |mut acc: Vec<Character>, next| {
+            .fold(Vec::new(), |mut acc: Vec<Character<A>>, next| {
                 acc.push(next.0);
                 acc.push(next.1);
                 acc
@@ -108,37 +136,67 @@ impl TryFrom<Vec<Plug>> for Plugs {
     }
 }
 
-impl Encode for Plugboard {
-    fn encode(&self, c: Character) -> Character {
+impl<A: Alphabet> From<Plugboard<A>> for Stecker<A> {
+    fn from(p: Plugboard<A>) -> Self {
+        Stecker::Plugboard(p)
+    }
+}
+
+impl<A: Alphabet> From<Uhr<A>> for Stecker<A> {
+    fn from(u: Uhr<A>) -> Self {
+        Stecker::Uhr(u)
+    }
+}
+
+impl<A: Alphabet> Encode<A> for Stecker<A> {
+    fn encode(&self, c: Character<A>) -> Character<A> {
+        match self {
+            Stecker::Plugboard(p) => p.encode(c),
+            Stecker::Uhr(u) => u.encode(c),
+        }
+    }
+}
+
+impl<A: Alphabet> Decode<A> for Stecker<A> {
+    fn decode(&self, c: Character<A>) -> Character<A> {
+        match self {
+            Stecker::Plugboard(p) => p.decode(c),
+            Stecker::Uhr(u) => u.decode(c),
+        }
+    }
+}
+
+impl<A: Alphabet> Encode<A> for Plugboard<A> {
+    fn encode(&self, c: Character<A>) -> Character<A> {
         self.cipher.encode(c)
     }
 }
 
-impl Decode for Plugboard {
-    fn decode(&self, c: Character) -> Character {
+impl<A: Alphabet> Decode<A> for Plugboard<A> {
+    fn decode(&self, c: Character<A>) -> Character<A> {
         self.cipher.decode(c)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{cipher::Encode, common::Character};
+    use crate::{alphabet::Latin, cipher::Encode, common::Character};
+    use itertools::Itertools;
 
     use super::{Plug, Plugboard, Plugs};
 
     #[test]
     fn empty_vec_cons() {
-        let _ =
-            Plugs::try_from(Vec::new()).expect("Should be able to construct an empty plugboard");
+        let _ = Plugs::<Latin>::try_from(Vec::new())
+            .expect("Should be able to construct an empty plugboard");
     }
 
     #[test]
     fn encode() {
-        let pb = Plugboard::try_from(Plugs::try_from(Vec::new()).unwrap())
+        let pb = Plugboard::try_from(Plugs::<Latin>::try_from(Vec::new()).unwrap())
             .expect("Should be able to construct an empty plugboard");
 
         ('A'..='Z')
-            .into_iter()
             .map(|c| Character::try_from(c).unwrap())
             .for_each(|c| {
                 let ct = pb.encode(c);
@@ -174,7 +232,6 @@ mod tests {
             .expect("Should be able to construct an empty plugboard");
 
         ('A'..='Z')
-            .into_iter()
             .map(|c| Character::try_from(c).unwrap())
             .for_each(|c| {
                 let ciphertext = pb.encode(c);
@@ -209,37 +266,50 @@ mod tests {
         .map(|p| Plug::try_from(p).unwrap())
         .collect();
 
-        match Plugs::try_from(plugs) {
-            Ok(_) => panic!("Should not be able to construct with more than 10 plugs"),
-            Err(_) => (),
+        if Plugs::try_from(plugs).is_ok() {
+            panic!("Should not be able to construct with more than 10 plugs")
         }
     }
 
+    #[test]
+    fn with_limit() {
+        let plugs = || -> Vec<Plug> {
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+                .chars()
+                .map(|c| Character::try_from(c).unwrap())
+                .tuples()
+                .map(|p: (Character, Character)| Plug::try_from(p).unwrap())
+                .collect()
+        };
+
+        assert!(Plugs::with_limit(plugs(), 13).is_ok());
+        assert!(Plugs::with_limit(plugs(), 12).is_err());
+        // No more plugs than the machine came with
+        assert!(Plugs::with_limit(plugs().into_iter().take(11).collect(), 20).is_ok());
+        assert!(Plugs::with_limit(plugs().into_iter().take(11).collect(), 0).is_err());
+    }
+
     #[test]
     fn same_character() {
-        let c = Character::try_from('A').unwrap();
+        let c = Character::<Latin>::try_from('A').unwrap();
         let x = Plug::try_from((c, c));
 
-        match x {
-            Ok(_) => {
-                panic!("Should not be able to construct a plug containing duplicate characters")
-            }
-            Err(_) => (),
+        if x.is_ok() {
+            panic!("Should not be able to construct a plug containing duplicate characters")
         }
     }
 
     #[test]
     fn duplicate_mapping() {
-        let a = Character::try_from('A').unwrap();
+        let a = Character::<Latin>::try_from('A').unwrap();
         let b = Character::try_from('B').unwrap();
         let c = Character::try_from('C').unwrap();
 
         let p1 = Plug::try_from((a, b)).unwrap();
         let p2 = Plug::try_from((a, c)).unwrap();
 
-        match Plugs::try_from(vec![p1, p2]) {
-            Ok(plugs) => panic!("Recieved {plugs:?}: Should not be able to construct plugs that contain non-unique mappings"),
-            Err(_) => (),
+        if let Ok(plugs) = Plugs::try_from(vec![p1, p2]) {
+            panic!("Recieved {plugs:?}: Should not be able to construct plugs that contain non-unique mappings")
         }
     }
 }
@@ -1,32 +1,192 @@
-use std::str::FromStr;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 
+use anyhow::Context;
+use bruh_moment::{bruh, Bruh};
 use serde::{Deserialize, Serialize};
-use strum_macros::{Display, EnumIter};
+use strum_macros::{Display, EnumIter, EnumString};
+use thiserror::Error;
 
 use crate::{
+    alphabet::{Alphabet, Digits, Latin},
     cipher::{Cipher, Encode},
-    common::Character,
+    common::{Character, ParsingError, Position},
 };
 
-pub struct Reflector {
-    cipher: Cipher,
+/// The reflector turns the signal around and sends it back through the rotors
+/// Some models let the operator set the reflector to any position, and the reflector of the Enigma G is moved by the
+/// rotors. A reflector at position `A` is wired exactly as listed
+pub struct Reflector<A: Alphabet = Latin> {
+    kind: ReflectorKind,
+    cipher: Cipher<A>,
+    position: Position<A>,
+}
+
+/// Either one of the fixed `Reflectors`, a wired UKW-D, the reflector of the Enigma Z or a reflector with a user
+/// defined wiring
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReflectorKind {
+    Builtin(Reflectors),
+    UkwD,
+    Z,
+    Custom,
+}
+
+impl Display for ReflectorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectorKind::Builtin(r) => write!(f, "{r}"),
+            ReflectorKind::UkwD => write!(f, "D"),
+            ReflectorKind::Z => write!(f, "Z"),
+            ReflectorKind::Custom => write!(f, "Custom"),
+        }
+    }
 }
 
 /// This enum represents each available reflector on the enigma machine
 /// Each reflector is a substitution cipher where the substitutions are reflective. For example, if a reflector
 /// substitutes `A` with `Z` it also substitutes `Z` with `A`.  
-/// The reflector does not move
-#[derive(EnumIter, Display, Serialize, Deserialize)]
+/// `BThin` and `CThin` are the thin reflectors of the M4, which leave room for a Greek wheel
+/// `Norway` belongs to the postwar Norwegian Enigma, `K` to the commercial Enigma D and K, `G` to the Abwehr Enigma G
+/// and `T` to the Enigma T. `Railway` belongs to the Reichsbahn Enigma, the Swiss Enigma K uses `K`
+#[derive(
+    EnumIter, EnumString, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
 pub enum Reflectors {
     A,
     B,
     C,
+    BThin,
+    CThin,
+    Norway,
+    K,
+    G,
+    T,
+    Railway,
+}
+
+#[derive(Error, Debug)]
+pub enum ReflectorError {
+    #[error(
+        "Recieved {0} pairs: UKW-D is wired with exactly 12 pairs besides the fixed J-Y contact"
+    )]
+    PairCount(usize),
+    #[error("J and Y are permanently wired together on the UKW-D and can not be rewired")]
+    FixedContact,
+    #[error("Reflector wiring must connect every contact to exactly one other contact")]
+    NotInvolution,
 }
 
 impl Reflector {
-    fn new(s: &str) -> Reflector {
-        let cipher = Cipher::from_str(s).unwrap();
-        Reflector { cipher: cipher }
+    fn new(r: Reflectors, s: &str) -> Reflector {
+        Reflector::from_cipher(ReflectorKind::Builtin(r), Cipher::from_str(s).unwrap())
+    }
+
+    /// Builds the field rewirable reflector UKW-D from 12 pairs of contacts
+    /// J and Y are always wired together, so the pairs must cover the remaining 24 letters exactly once
+    pub fn ukw_d(pairs: &[(Character, Character)]) -> Result<Reflector, ReflectorError> {
+        if pairs.len() != 12 {
+            return Err(ReflectorError::PairCount(pairs.len()));
+        }
+
+        let j = Character::try_from('J').unwrap();
+        let y = Character::try_from('Y').unwrap();
+        if pairs
+            .iter()
+            .any(|(l, r)| [j, y].contains(l) || [j, y].contains(r))
+        {
+            return Err(ReflectorError::FixedContact);
+        }
+
+        Reflector::from_pairs(ReflectorKind::UkwD, pairs.iter().chain([(j, y)].iter()))
+    }
+}
+
+impl Reflector<Digits> {
+    /// The settable reflector of the Enigma Z
+    pub fn z() -> Reflector<Digits> {
+        Reflector::from_cipher(ReflectorKind::Z, Cipher::from_str("2507918364").unwrap())
+    }
+}
+
+impl<A: Alphabet> Reflector<A> {
+    /// A reflector with a user defined wiring, which lists the symbol each contact is wired to in the order of
+    /// `A::SYMBOLS`. Fails unless every contact is wired to a different contact that is wired back to it
+    pub fn from_wiring(wiring: &str) -> Result<Reflector<A>, Bruh> {
+        let cipher = Cipher::from_str(wiring).context("Invalid wiring for a reflector")?;
+        let reflector = Reflector::from_cipher(ReflectorKind::Custom, cipher);
+        match reflector.is_involution() {
+            true => Ok(reflector),
+            false => Err(bruh!(ReflectorError::NotInvolution)),
+        }
+    }
+
+    pub fn kind(&self) -> ReflectorKind {
+        self.kind
+    }
+
+    /// Sets the reflector to the letter `position`
+    pub fn with_position(self, position: char) -> Result<Reflector<A>, ParsingError> {
+        Ok(Reflector {
+            position: Position::try_from(position)?,
+            ..self
+        })
+    }
+
+    pub fn position(&self) -> Position<A> {
+        self.position
+    }
+
+    /// Encodes a given char through the reflector turned to `position`, ignoring the position it was set to
+    pub fn encode_at(&self, c: Character<A>, position: Position<A>) -> Character<A> {
+        self.cipher.encode(c + position) - position
+    }
+
+    fn from_cipher(kind: ReflectorKind, cipher: Cipher<A>) -> Reflector<A> {
+        Reflector {
+            kind,
+            cipher,
+            position: Position::try_from(0).unwrap(),
+        }
+    }
+
+    /// Builds a reflector from pairs of contacts, which must connect every contact to exactly one other contact
+    fn from_pairs<'a>(
+        kind: ReflectorKind,
+        pairs: impl Iterator<Item = &'a (Character<A>, Character<A>)>,
+    ) -> Result<Reflector<A>, ReflectorError> {
+        let mut wiring: Vec<Option<Character<A>>> = vec![None; A::SIZE];
+        for (l, r) in pairs {
+            for (from, to) in [(l, r), (r, l)] {
+                let contact = &mut wiring[from.get_offset() as usize];
+                if contact.is_some() || from == to {
+                    return Err(ReflectorError::NotInvolution);
+                }
+                *contact = Some(*to);
+            }
+        }
+
+        let wiring: Vec<Character<A>> = wiring
+            .into_iter()
+            .collect::<Option<Vec<Character<A>>>>()
+            .ok_or(ReflectorError::NotInvolution)?;
+        let cipher = Cipher::try_from(wiring).map_err(|_| ReflectorError::NotInvolution)?;
+        let reflector = Reflector::from_cipher(kind, cipher);
+
+        match reflector.is_involution() {
+            true => Ok(reflector),
+            false => Err(ReflectorError::NotInvolution),
+        }
+    }
+
+    /// Every contact must be wired to a different contact, which is wired back to it
+    fn is_involution(&self) -> bool {
+        Character::all().all(|c| {
+            let reflected = self.encode(c);
+            reflected != c && self.encode(reflected) == c
+        })
     }
 }
 
@@ -36,19 +196,26 @@ impl From<Reflectors> for Reflector {
     /// Returns a pre-generated reflector given a member of the `Reflectors` enum
     fn from(r: Reflectors) -> Self {
         match r {
-            Reflectors::A => Reflector::new("EJMZALYXVBWFCRQUONTSPIKHGD"),
-            Reflectors::B => Reflector::new("YRUHQSLDPXNGOKMIEBFZCWVJAT"),
-            Reflectors::C => Reflector::new("FVPJIAOYEDRZXWGCTKUQSBNMHL"),
+            Reflectors::A => Reflector::new(r, "EJMZALYXVBWFCRQUONTSPIKHGD"),
+            Reflectors::B => Reflector::new(r, "YRUHQSLDPXNGOKMIEBFZCWVJAT"),
+            Reflectors::C => Reflector::new(r, "FVPJIAOYEDRZXWGCTKUQSBNMHL"),
+            Reflectors::BThin => Reflector::new(r, "ENKQAUYWJICOPBLMDXZVFTHRGS"),
+            Reflectors::CThin => Reflector::new(r, "RDOBJNTKVEHMLFCWZAXGYIPSUQ"),
+            Reflectors::Norway => Reflector::new(r, "MOWJYPUXNDSRAIBFVLKZGQCHET"),
+            Reflectors::K => Reflector::new(r, "IMETCGFRAYSQBZXWLHKDVUPOJN"),
+            Reflectors::G => Reflector::new(r, "RULQMZJSYGOCETKWDAHNBXPVIF"),
+            Reflectors::T => Reflector::new(r, "GEKPBTAUMOCNILJDXZYFHWVQSR"),
+            Reflectors::Railway => Reflector::new(r, "QYHOGNECVPUZTFDJAXWMKISRBL"),
         }
     }
 }
 
-impl Encode for Reflector {
+impl<A: Alphabet> Encode<A> for Reflector<A> {
     /// Encodes a given char through the reflector.
     /// Given the properties of the reflector, if the output of this function was fed through this function,
     /// you would get back the original char
-    fn encode(&self, c: Character) -> Character {
-        self.cipher.encode(c)
+    fn encode(&self, c: Character<A>) -> Character<A> {
+        self.encode_at(c, self.position)
     }
 }
 
@@ -57,6 +224,16 @@ mod tests {
     use crate::{cipher::Encode, common::Character};
 
     use super::{Reflector, Reflectors};
+    use strum::IntoEnumIterator;
+
+    fn pairs(s: &str) -> Vec<(Character, Character)> {
+        s.split_whitespace()
+            .map(|p| {
+                let mut c = p.chars().map(|c| Character::try_from(c).unwrap());
+                (c.next().unwrap(), c.next().unwrap())
+            })
+            .collect()
+    }
 
     #[test]
     fn construct_a() {
@@ -73,12 +250,21 @@ mod tests {
         let _ = Reflector::from(Reflectors::C);
     }
 
+    #[test]
+    fn construct_b_thin() {
+        let _ = Reflector::from(Reflectors::BThin);
+    }
+
+    #[test]
+    fn construct_c_thin() {
+        let _ = Reflector::from(Reflectors::CThin);
+    }
+
     #[test]
     fn codec() {
         let reflector = Reflector::from(Reflectors::A);
 
         ('A'..='Z')
-            .into_iter()
             .map(|c| Character::try_from(c).unwrap())
             .for_each(|c| {
                 let ciphertext = reflector.encode(c);
@@ -87,4 +273,58 @@ mod tests {
                 assert_eq!(c, plaintext)
             })
     }
+
+    #[test]
+    fn builtin_involutions() {
+        Reflectors::iter().for_each(|r| assert!(Reflector::from(r).is_involution()))
+    }
+
+    #[test]
+    fn z() {
+        let reflector = Reflector::z();
+
+        assert!(reflector.is_involution());
+        assert!(reflector.with_position('7').unwrap().is_involution());
+        assert!(Reflector::z().with_position('A').is_err());
+    }
+
+    #[test]
+    fn ukw_d() {
+        let reflector = Reflector::ukw_d(&pairs("AF BP CG DQ EN HX IW KS LT MR OU VZ")).unwrap();
+        let j = Character::try_from('J').unwrap();
+        let y = Character::try_from('Y').unwrap();
+
+        assert_eq!(reflector.encode(j), y);
+        assert!(reflector.is_involution());
+    }
+
+    #[test]
+    fn ukw_d_pair_count() {
+        assert!(Reflector::ukw_d(&pairs("AF BP CG DQ EN HX IW KS LT MR OU")).is_err());
+    }
+
+    #[test]
+    fn ukw_d_fixed_contact() {
+        assert!(Reflector::ukw_d(&pairs("AF BP CG DQ EN HX IW KS LT MR OU VJ")).is_err());
+    }
+
+    #[test]
+    fn ukw_d_duplicate_contact() {
+        assert!(Reflector::ukw_d(&pairs("AF BP CG DQ EN HX IW KS LT MR OU VA")).is_err());
+    }
+
+    #[test]
+    fn position() {
+        // Turning reflector B to position B shifts its wiring by one contact
+        let reflector = Reflector::from(Reflectors::B).with_position('B').unwrap();
+        let a = Character::try_from('A').unwrap();
+
+        assert_eq!(reflector.encode(a), Character::try_from('Q').unwrap());
+        assert!(reflector.is_involution());
+    }
+
+    #[test]
+    fn invalid_position() {
+        assert!(Reflector::from(Reflectors::B).with_position('1').is_err());
+    }
 }
@@ -1,10 +1,18 @@
-use std::{hash::Hash, str::FromStr};
+use std::{
+    borrow::Cow,
+    fmt::{Display, Formatter},
+    hash::Hash,
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
 
 use crate::{
+    alphabet::{Alphabet, Digits, Latin},
     cipher::{Cipher, Decode, Encode},
     common::{Character, Position},
+    stepping::{Drive, Ratchet, Stepping},
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use bruh_moment::Bruh;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -12,6 +20,12 @@ use strum_macros::{Display, EnumIter, EnumString};
 
 /// This enum represents each available rotor for the real life enigma machine
 /// Each rotor is a simple substition cipher plus one or two notches which would allow the next rotor in the sequence to rotate
+/// The Greek wheels `Beta` and `Gamma` of the M4 have no notches and never step. They only fit in the fourth position,
+/// next to a thin reflector
+/// `NorwayI` to `NorwayV` are the rewired rotors of the postwar Norwegian Enigma, `KI` to `KIII` the rotors of the
+/// commercial Enigma D and K, `GI` to `GIII` the many-notched rotors of the Abwehr Enigma G-312, and `TI` to `TVIII`
+/// the five notch rotors of the Enigma T (Tirpitz). `RailwayI` to `RailwayIII` belong to the Reichsbahn Enigma and
+/// `SwissKI` to `SwissKIII` to the Swiss Enigma K
 #[derive(
     EnumString, EnumIter, Hash, PartialEq, Eq, Clone, Copy, Display, Debug, Serialize, Deserialize,
 )]
@@ -24,67 +38,597 @@ pub enum Rotors {
     VI,
     VII,
     VIII,
+    Beta,
+    Gamma,
+    NorwayI,
+    NorwayII,
+    NorwayIII,
+    NorwayIV,
+    NorwayV,
+    KI,
+    KII,
+    KIII,
+    GI,
+    GII,
+    GIII,
+    TI,
+    TII,
+    TIII,
+    TIV,
+    TV,
+    TVI,
+    TVII,
+    TVIII,
+    RailwayI,
+    RailwayII,
+    RailwayIII,
+    SwissKI,
+    SwissKII,
+    SwissKIII,
+}
+
+impl Rotors {
+    /// Whether this is one of the non-stepping Greek wheels of the M4
+    pub fn is_greek(&self) -> bool {
+        matches!(self, Rotors::Beta | Rotors::Gamma)
+    }
+
+    /// Wiring and turnover notches of the rotor
+    fn wiring(&self) -> (&'static str, &'static [char]) {
+        match self {
+            Rotors::I => ("EKMFLGDQVZNTOWYHXUSPAIBRCJ", &['Q']),
+            Rotors::II => ("AJDKSIRUXBLHWTMCQGZNPYFVOE", &['E']),
+            Rotors::III => ("BDFHJLCPRTXVZNYEIWGAKMUSQO", &['V']),
+            Rotors::IV => ("ESOVPZJAYQUIRHXLNFTGKDCMWB", &['J']),
+            Rotors::V => ("VZBRGITYUPSDNHLXAWMJQOFECK", &['Z']),
+            Rotors::VI => ("JPGVOUMFYQBENHZRDKASXLICTW", &['Z', 'M']),
+            Rotors::VII => ("NZJHGRCXMYSWBOUFAIVLPEKQDT", &['Z', 'M']),
+            Rotors::VIII => ("FKQHTLXOCBJSPDZRAMEWNIUYGV", &['Z', 'M']),
+            Rotors::Beta => ("LEYJVCNIXWPBQMDRTAKZGFUHOS", &[]),
+            Rotors::Gamma => ("FSOKANUERHMBTIYCWLQPZXVGJD", &[]),
+            Rotors::NorwayI => ("WTOKASUYVRBXJHQCPZEFMDINLG", &['Q']),
+            Rotors::NorwayII => ("GJLPUBSWEMCTQVHXAOFZDRKYNI", &['E']),
+            Rotors::NorwayIII => ("JWFMHNBPUSDYTIXVZGRQLAOEKC", &['V']),
+            Rotors::NorwayIV => ("ESOVPZJAYQUIRHXLNFTGKDCMWB", &['J']),
+            Rotors::NorwayV => ("HEJXQOTZBVFDASCILWPGYNMURK", &['Z']),
+            Rotors::KI => ("LPGSZMHAEOQKVXRFYBUTNICJDW", &['Y']),
+            Rotors::KII => ("SLVGBTFXJQOHEWIRZYAMKPCNDU", &['E']),
+            Rotors::KIII => ("CJGDPSHKTURAWZXFMYNQOBVLIE", &['N']),
+            Rotors::GI => (
+                "DMTWSILRUYQNKFEJCAZBPGXOHV",
+                &[
+                    'S', 'U', 'V', 'W', 'Z', 'A', 'B', 'C', 'E', 'F', 'G', 'I', 'K', 'L', 'O', 'P',
+                    'Q',
+                ],
+            ),
+            Rotors::GII => (
+                "HQZGPJTMOBLNCIFDYAWVEUSRKX",
+                &[
+                    'S', 'T', 'V', 'Y', 'Z', 'A', 'C', 'D', 'F', 'G', 'H', 'K', 'M', 'N', 'Q',
+                ],
+            ),
+            Rotors::GIII => (
+                "UQNTLSZFMREHDPXKIBVYGJCWOA",
+                &['U', 'W', 'X', 'A', 'E', 'F', 'H', 'K', 'M', 'N', 'R'],
+            ),
+            Rotors::TI => ("KPTYUELOCVGRFQDANJMBSWHZXI", &['W', 'Z', 'E', 'K', 'Q']),
+            Rotors::TII => ("UPHZLWEQMTDJXCAKSOIGVBYFNR", &['W', 'Z', 'F', 'L', 'R']),
+            Rotors::TIII => ("QUDLYRFEKONVZAXWHMGPJBSICT", &['W', 'Z', 'E', 'K', 'Q']),
+            Rotors::TIV => ("CIWTBKXNRESPFLYDAGVHQUOJZM", &['W', 'Z', 'F', 'L', 'R']),
+            Rotors::TV => ("UAXGISNJBVERDYLFZWTPCKOHMQ", &['Y', 'C', 'F', 'K', 'R']),
+            Rotors::TVI => ("XFUZGALVHCNYSEWQTDMRBKPIOJ", &['X', 'E', 'I', 'M', 'Q']),
+            Rotors::TVII => ("BJVFTXPLNAYOZIKWGDQERUCHSM", &['Y', 'C', 'F', 'K', 'R']),
+            Rotors::TVIII => ("YMTPNZHWKODAJXELUQVGCBISFR", &['X', 'E', 'I', 'M', 'Q']),
+            Rotors::RailwayI => ("JGDQOXUSCAMIFRVTPNEWKBLZYH", &['N']),
+            Rotors::RailwayII => ("NTZPSFBOKMWRCJDIVLAEYUXHGQ", &['E']),
+            Rotors::RailwayIII => ("JVIUBHTCDYAKEQZPOSGXNRMWFL", &['Y']),
+            Rotors::SwissKI => ("PEZUOHXSCVFMTBGLRINQJWAYDK", &['Y']),
+            Rotors::SwissKII => ("ZOUESYDKFWPCIQXHMVBLGNJRAT", &['E']),
+            Rotors::SwissKIII => ("EHRVXGAOBQUSIMZFLYNWKTPDJC", &['N']),
+        }
+    }
+}
+
+/// The rotors of the Enigma Z, which enciphers the ten digits `0` to `9` instead of letters
+/// Each rotor is assumed to turn the next one over as it moves on from `9` to `0`, like the wheels of a counter
+#[derive(
+    EnumString, EnumIter, Hash, PartialEq, Eq, Clone, Copy, Display, Debug, Serialize, Deserialize,
+)]
+pub enum ZRotors {
+    I,
+    II,
+    III,
+}
+
+impl ZRotors {
+    /// Wiring and turnover notches of the rotor
+    fn wiring(&self) -> (&'static str, &'static [char]) {
+        match self {
+            ZRotors::I => ("9641827035", &['9']),
+            ZRotors::II => ("6584109723", &['9']),
+            ZRotors::III => ("4358162079", &['9']),
+        }
+    }
+}
+
+/// A rotor with user defined wiring, for captured, hypothetical or teaching wirings
+/// `wiring` lists the letter each contact from `A` to `Z` is wired to, `notches` lists every letter at which
+/// the rotor turns the next rotor over. Construct with `CustomRotor::new` to validate the definition up front
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomRotor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    wiring: String,
+    #[serde(default)]
+    notches: String,
+}
+
+/// Either one of the built in `Rotors`, a `CustomRotor` or one of the `ZRotors`
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RotorKind {
+    Builtin(Rotors),
+    Custom(CustomRotor),
+    #[serde(skip)]
+    Z(ZRotors),
+}
+
+impl CustomRotor {
+    pub fn new(wiring: &str, notches: &str, name: Option<&str>) -> Result<CustomRotor, Bruh> {
+        CustomRotor::for_alphabet::<Latin>(
+            &wiring.to_ascii_uppercase(),
+            &notches.to_ascii_uppercase(),
+            name,
+        )
+    }
+
+    /// A rotor for a machine with the alphabet `A`. `wiring` lists the symbol each contact is wired to, in the order
+    /// of `A::SYMBOLS`
+    pub fn for_alphabet<A: Alphabet>(
+        wiring: &str,
+        notches: &str,
+        name: Option<&str>,
+    ) -> Result<CustomRotor, Bruh> {
+        let rotor = CustomRotor {
+            name: name.map(String::from),
+            wiring: wiring.to_string(),
+            notches: notches.to_string(),
+        };
+        let first = A::SYMBOLS[0];
+        Rotor::<A>::try_from((RotorKind::Custom(rotor.clone()), first, first))?;
+        Ok(rotor)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl RotorKind {
+    fn is_greek(&self) -> bool {
+        match self {
+            RotorKind::Builtin(r) => r.is_greek(),
+            RotorKind::Custom(_) | RotorKind::Z(_) => false,
+        }
+    }
+}
+
+impl From<Rotors> for RotorKind {
+    fn from(r: Rotors) -> Self {
+        RotorKind::Builtin(r)
+    }
+}
+
+impl From<ZRotors> for RotorKind {
+    fn from(r: ZRotors) -> Self {
+        RotorKind::Z(r)
+    }
+}
+
+impl From<CustomRotor> for RotorKind {
+    fn from(r: CustomRotor) -> Self {
+        RotorKind::Custom(r)
+    }
+}
+
+impl Display for RotorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RotorKind::Builtin(r) => write!(f, "{r}"),
+            RotorKind::Custom(r) => write!(f, "{}", r.name().unwrap_or("Custom")),
+            RotorKind::Z(r) => write!(f, "Z{r}"),
+        }
+    }
 }
 /// Individual rotor used in the rotor mechanism
-pub struct Rotor {
-    position: Position,
-    cipher: Cipher,
-    notches: Notches,
+/// The ring setting (Ringstellung) rotates the wiring relative to the alphabet ring, which carries the notches
+/// and the letter shown in the window
+pub struct Rotor<A: Alphabet = Latin> {
+    kind: RotorKind,
+    position: Position<A>,
+    ring: Position<A>,
+    cipher: Cipher<A>,
+    notches: Notches<A>,
+    stationary: bool,
+    reversed: bool,
 }
 
 #[derive(Hash, Debug)]
-struct Notches(Vec<Position>);
+struct Notches<A: Alphabet = Latin>(Vec<Position<A>>);
+
+/// The rotors of the machine, ordered from the entry side (the fast, rightmost rotor) to the reflector side
+///
+/// Rotors are moved by a `Stepping`, by default the pawls and ratchets of the Enigma. Stationary rotors such as the
+/// Greek wheels have no pawl and keep their position.
+/// On machines with a moving reflector the reflector is driven like one more rotor after the last moving rotor.
+pub struct RotorConfig<A: Alphabet = Latin> {
+    rotors: Vec<Rotor<A>>,
+    reflector: Option<Position<A>>,
+    stepping: Box<dyn Stepping<A>>,
+    schedule: OnceLock<Schedule<A>>,
+}
+
+/// Rotor positions for every key press, recorded until the sequence starts repeating
+///
+/// The stepping state is finite, so the positions after `n` key presses can be looked up for any `n` without
+/// stepping through every intermediate position. Machines with many moving rotors can take far more key presses to
+/// repeat than are worth recording, so at most `RECORDED` key presses are kept. Past them the positions are stepped
+/// from checkpoints left every `STRIDE` key presses, which only grow with the length of the message
+struct Schedule<A: Alphabet = Latin> {
+    width: usize,
+    states: Vec<Position<A>>,
+    /// The length of the sequence before the cycle and the length of the cycle, if it fits in the recorded states
+    cycle: Option<(usize, usize)>,
+    checkpoints: RwLock<Vec<Position<A>>>,
+}
+
+/// The most key presses a `Schedule` records, enough for the whole cycle of any Enigma model
+const RECORDED: usize = 1 << 20;
+
+/// Key presses between two checkpoints past the recorded ones
+const STRIDE: usize = 64;
+
+impl<A: Alphabet> RotorConfig<A> {
+    fn new(rotors: Vec<Rotor<A>>) -> RotorConfig<A> {
+        RotorConfig::build(rotors, None, Box::new(Ratchet))
+    }
+
+    /// Rotors in any number and order, for rotor machines other than the Enigma. The rotor order is not checked
+    /// against the Enigma models, see `RotorConfig::try_from` for that. Fails if there are no rotors
+    pub fn from_rotors(rotors: Vec<Rotor<A>>) -> Result<RotorConfig<A>, Bruh> {
+        match rotors.is_empty() {
+            true => Err(anyhow!("A rotor configuration needs at least one rotor")),
+            false => Ok(RotorConfig::new(rotors)),
+        }
+    }
+
+    /// `reflector` is the starting position of a reflector that takes part in the stepping
+    fn build(
+        rotors: Vec<Rotor<A>>,
+        reflector: Option<Position<A>>,
+        stepping: Box<dyn Stepping<A>>,
+    ) -> RotorConfig<A> {
+        RotorConfig {
+            rotors,
+            reflector,
+            stepping,
+            schedule: OnceLock::new(),
+        }
+    }
+
+    /// Positions the rotors, and a driven reflector after them, were set to
+    fn start(&self) -> Vec<Position<A>> {
+        self.rotors
+            .iter()
+            .map(|r| r.position)
+            .chain(self.reflector)
+            .collect()
+    }
+
+    /// The schedule is only worked out once the first character is enciphered, so that changing the reflector or
+    /// the stepping while building a machine does not record the positions over and over
+    fn schedule(&self) -> &Schedule<A> {
+        self.schedule
+            .get_or_init(|| Schedule::new(self.start(), |p| self.step(p)))
+    }
+
+    fn step(&self, positions: &mut [Position<A>]) {
+        self.stepping.step(&self.rotors, positions)
+    }
+
+    /// Positions of every rotor, and of a driven reflector after them, while the `n`th character is enciphered
+    fn positions_at(&self, n: usize) -> Cow<'_, [Position<A>]> {
+        self.schedule().at(n, |p| self.step(p))
+    }
+
+    /// Lets the reflector, starting at `position`, move with the rotors
+    pub fn drive_reflector(self, position: Position<A>) -> RotorConfig<A> {
+        RotorConfig::build(self.rotors, Some(position), self.stepping)
+    }
+
+    /// Moves the rotors with `stepping` instead of pawls
+    pub fn with_stepping(self, stepping: impl Stepping<A> + 'static) -> RotorConfig<A> {
+        RotorConfig::build(self.rotors, self.reflector, Box::new(stepping))
+    }
+
+    /// Moves the rotors with one of the built in steppings
+    pub fn with_drive(self, drive: Drive) -> RotorConfig<A> {
+        RotorConfig::build(self.rotors, self.reflector, drive.stepping())
+    }
+
+    /// The built in stepping that moves the rotors, `None` for any other `Stepping`
+    pub fn drive(&self) -> Option<Drive> {
+        self.stepping.drive()
+    }
+
+    /// The kind of every rotor, from the fast rotor to the slow rotor
+    pub fn kinds(&self) -> impl Iterator<Item = &RotorKind> {
+        self.rotors.iter().map(|r| &r.kind)
+    }
+
+    /// Whether the reflector is driven by the rotors
+    pub fn drives_reflector(&self) -> bool {
+        self.reflector.is_some()
+    }
+
+    /// Position of the reflector while the `n`th character is enciphered, if the reflector is driven by the rotors
+    pub fn reflector_position_at(&self, n: usize) -> Option<Position<A>> {
+        self.reflector
+            .map(|_| self.positions_at(n)[self.rotors.len()])
+    }
+
+    /// Positions of every rotor, and of a driven reflector after them, once `presses` keys have been pressed
+    pub fn positions_after(&self, presses: usize) -> Vec<Position<A>> {
+        match presses {
+            0 => self.start(),
+            n => self.positions_at(n - 1).into_owned(),
+        }
+    }
+
+    /// Turns the rotors, and a driven reflector after them, to `positions`. The ring settings stay as they are
+    pub fn set_positions(&mut self, positions: &[Position<A>]) -> Result<(), Bruh> {
+        let width = self.rotors.len() + usize::from(self.reflector.is_some());
+        if positions.len() != width {
+            return Err(anyhow!(
+                "Recieved {} positions, the machine has {width} moving parts",
+                positions.len()
+            ));
+        }
+
+        self.rotors
+            .iter_mut()
+            .zip(positions)
+            .for_each(|(r, p)| r.position = *p);
+        if let Some(reflector) = self.reflector.as_mut() {
+            *reflector = positions[width - 1];
+        }
+        self.schedule = OnceLock::new();
+        Ok(())
+    }
+
+    fn from_settings(
+        settings: impl Iterator<Item = (RotorKind, char, char)>,
+    ) -> Result<RotorConfig<A>, Bruh> {
+        let v: Vec<Rotor<A>> = settings.map(Rotor::try_from).try_collect()?;
+        RotorConfig::try_from(v)
+    }
+
+    /// Encodes `c` as the `n`th character of a message. The rotors step before every character, including the first
+    pub fn encode_at(&self, c: Character<A>, n: usize) -> Character<A> {
+        self.rotors
+            .iter()
+            .zip(self.positions_at(n).iter())
+            .fold(c, |c, (rotor, p)| rotor.encode_at(c, *p))
+    }
+
+    pub fn decode_at(&self, c: Character<A>, n: usize) -> Character<A> {
+        self.rotors
+            .iter()
+            .zip(&self.positions_at(n)[..self.rotors.len()])
+            .rev()
+            .fold(c, |c, (rotor, p)| rotor.decode_at(c, *p))
+    }
+}
+
+impl<A: Alphabet> Schedule<A> {
+    /// Records the positions produced by `step`, starting with the first step away from `start`
+    fn new(start: Vec<Position<A>>, step: impl Fn(&mut [Position<A>])) -> Schedule<A> {
+        let width = start.len();
+        let mut first = start;
+        step(&mut first);
+
+        let cycle = Schedule::cycle(&first, &step);
+        let recorded = cycle.map_or(RECORDED, |(tail, period)| tail + period);
+        let mut states = Vec::with_capacity(recorded * width);
+        let mut current = first;
+        for _ in 0..recorded {
+            states.extend_from_slice(&current);
+            step(&mut current);
+        }
+
+        Schedule {
+            width,
+            states,
+            cycle,
+            checkpoints: RwLock::new(Vec::new()),
+        }
+    }
 
-pub struct RotorConfig(Vec<Rotor>);
+    /// Brent's cycle detection: find the period first, then the length of the sequence before the cycle. Gives up
+    /// once the cycle can no longer fit in `RECORDED` key presses
+    fn cycle(first: &[Position<A>], step: impl Fn(&mut [Position<A>])) -> Option<(usize, usize)> {
+        let mut power = 1;
+        let mut period = 1;
+        let mut steps = 1;
+        let mut tortoise = first.to_vec();
+        let mut hare = first.to_vec();
+        step(&mut hare);
+        while tortoise != hare {
+            if steps == RECORDED {
+                return None;
+            }
+            if power == period {
+                tortoise.copy_from_slice(&hare);
+                power *= 2;
+                period = 0;
+            }
+            step(&mut hare);
+            period += 1;
+            steps += 1;
+        }
+
+        tortoise.copy_from_slice(first);
+        hare.copy_from_slice(first);
+        for _ in 0..period {
+            step(&mut hare);
+        }
+        let mut tail = 0;
+        while tortoise != hare {
+            step(&mut tortoise);
+            step(&mut hare);
+            tail += 1;
+        }
+        Some((tail, period))
+    }
 
-impl RotorConfig {
-    pub fn encode_at(&self, c: Character, n: usize) -> Character {
-        let encode_first_rotor = self.0[0].encode_at(c, n);
-        let n = self.0[0].get_num_advances(n);
-        let encode_second_rotor = self.0[1].encode_at(encode_first_rotor, n);
-        let n = self.0[1].get_num_advances(n);
-        let encode_third_rotor = self.0[2].encode_at(encode_second_rotor, n);
-        encode_third_rotor
+    /// Positions of every rotor while the `n`th character is enciphered. `step` must be the stepping the schedule
+    /// was recorded with
+    fn at(&self, n: usize, step: impl Fn(&mut [Position<A>])) -> Cow<'_, [Position<A>]> {
+        let i = match self.cycle {
+            Some((tail, period)) if n >= tail => tail + (n - tail) % period,
+            _ => n,
+        };
+        let recorded = self.states.len() / self.width;
+        if i < recorded {
+            return Cow::Borrowed(&self.states[i * self.width..(i + 1) * self.width]);
+        }
+
+        // Checkpoint `k` holds the positions `(k + 1) * STRIDE` key presses after the last recorded ones
+        let past = i - recorded + 1;
+        let checkpoint = past / STRIDE;
+        let mut positions = match checkpoint {
+            0 => self.states[(recorded - 1) * self.width..].to_vec(),
+            k => self.checkpoint(k - 1, &step),
+        };
+        for _ in 0..past % STRIDE {
+            step(&mut positions);
+        }
+        Cow::Owned(positions)
     }
 
-    pub fn decode_at(&self, c: Character, n: usize) -> Character {
-        let r1_advances = n;
-        let r2_advances = self.0[0].get_num_advances(n);
-        let r3_advances = self.0[1].get_num_advances(r2_advances);
+    /// The positions at checkpoint `k`, stepping to it from the last checkpoint if it was not reached before
+    fn checkpoint(&self, k: usize, step: impl Fn(&mut [Position<A>])) -> Vec<Position<A>> {
+        let range = k * self.width..(k + 1) * self.width;
+        if let Some(positions) = self.checkpoints.read().unwrap().get(range.clone()) {
+            return positions.to_vec();
+        }
 
-        let decode_third_rotor = self.0[2].decode_at(c, r3_advances);
-        let decode_second_rotor = self.0[1].decode_at(decode_third_rotor, r2_advances);
-        let decode_first_rotor = self.0[0].decode_at(decode_second_rotor, r1_advances);
-        decode_first_rotor
+        let mut checkpoints = self.checkpoints.write().unwrap();
+        while checkpoints.len() < range.end {
+            let mut positions = match checkpoints.len() {
+                0 => self.states[self.states.len() - self.width..].to_vec(),
+                len => checkpoints[len - self.width..].to_vec(),
+            };
+            for _ in 0..STRIDE {
+                step(&mut positions);
+            }
+            checkpoints.extend_from_slice(&positions);
+        }
+        checkpoints[range].to_vec()
     }
 }
 
 impl TryFrom<[(Rotors, char); 3]> for RotorConfig {
     type Error = Bruh;
 
+    /// Every rotor uses ring setting `A`
     fn try_from(value: [(Rotors, char); 3]) -> Result<Self, Self::Error> {
-        let iter = value.iter();
-        let rawcount = iter.clone().count();
-        let unique = iter.clone().map(|(r, _)| r).unique().count();
+        RotorConfig::try_from(value.map(|(r, p)| (r, p, 'A')))
+    }
+}
+
+impl TryFrom<[(Rotors, char, char); 3]> for RotorConfig {
+    type Error = Bruh;
+
+    /// Each entry is a rotor, its starting position and its ring setting
+    fn try_from(value: [(Rotors, char, char); 3]) -> Result<Self, Self::Error> {
+        RotorConfig::from_settings(value.into_iter().map(|(r, p, ring)| (r.into(), p, ring)))
+    }
+}
+
+impl TryFrom<[(Rotors, char); 4]> for RotorConfig {
+    type Error = Bruh;
+
+    /// Every rotor uses ring setting `A`
+    fn try_from(value: [(Rotors, char); 4]) -> Result<Self, Self::Error> {
+        RotorConfig::try_from(value.map(|(r, p)| (r, p, 'A')))
+    }
+}
+
+impl TryFrom<[(Rotors, char, char); 4]> for RotorConfig {
+    type Error = Bruh;
+
+    /// Rotor configuration for the four rotor M4. The last entry must be the Greek wheel `Beta` or `Gamma`
+    fn try_from(value: [(Rotors, char, char); 4]) -> Result<Self, Self::Error> {
+        RotorConfig::from_settings(value.into_iter().map(|(r, p, ring)| (r.into(), p, ring)))
+    }
+}
+
+impl TryFrom<Vec<(Rotors, char, char)>> for RotorConfig {
+    type Error = Bruh;
+
+    /// Builds a three or four rotor configuration, see the array conversions
+    fn try_from(value: Vec<(Rotors, char, char)>) -> Result<Self, Self::Error> {
+        RotorConfig::from_settings(value.into_iter().map(|(r, p, ring)| (r.into(), p, ring)))
+    }
+}
+
+impl TryFrom<Vec<(RotorKind, char, char)>> for RotorConfig {
+    type Error = Bruh;
 
-        if rawcount == 3 && unique == 3 {
-            let v: Vec<Rotor> = iter
-                .clone()
-                .map(|(r, c)| Rotor::try_from((*r, *c)))
-                .try_collect()?;
+    /// Builds a three or four rotor configuration that may mix built in and custom rotors
+    fn try_from(value: Vec<(RotorKind, char, char)>) -> Result<Self, Self::Error> {
+        RotorConfig::from_settings(value.into_iter())
+    }
+}
+
+impl TryFrom<[(ZRotors, char, char); 3]> for RotorConfig<Digits> {
+    type Error = Bruh;
+
+    /// Rotor configuration for the Enigma Z. Positions and ring settings are digits
+    fn try_from(value: [(ZRotors, char, char); 3]) -> Result<Self, Self::Error> {
+        let v: Vec<Rotor<Digits>> = value.into_iter().map(Rotor::try_from).try_collect()?;
+        RotorConfig::try_from(v)
+    }
+}
+
+impl<A: Alphabet> TryFrom<Vec<Rotor<A>>> for RotorConfig<A> {
+    type Error = Bruh;
 
-            return Ok(RotorConfig(v));
+    /// Checks the rotor order. Three rotors make up an Enigma I or M3,
+    /// four rotors an M4 where the last (leftmost) rotor must be a Greek wheel
+    fn try_from(value: Vec<Rotor<A>>) -> Result<Self, Self::Error> {
+        let unique = value.iter().map(|r| &r.kind).unique().count();
+        let greek: Vec<bool> = value.iter().map(|r| r.kind.is_greek()).collect();
+
+        let valid_order = matches!(
+            greek.as_slice(),
+            [false, false, false] | [false, false, false, true]
+        );
+
+        if unique == value.len() && valid_order {
+            return Ok(RotorConfig::new(value));
         };
         Err(anyhow!("Invalid rotor configuration"))
     }
 }
 
-impl FromIterator<Rotor> for RotorConfig {
-    fn from_iter<T: IntoIterator<Item = Rotor>>(iter: T) -> Self {
-        let num = iter.into_iter().count();
-        match num {
+impl<A: Alphabet> FromIterator<Rotor<A>> for RotorConfig<A> {
+    fn from_iter<T: IntoIterator<Item = Rotor<A>>>(iter: T) -> Self {
+        let rotors: Vec<Rotor<A>> = iter.into_iter().collect();
+        match rotors.len() {
             0..=2 => panic!("Too few items in iterator to create rotorconfig"),
-            3 => todo!(),
+            3 | 4 => RotorConfig::new(rotors),
             _ => panic!("Too many items in iterator to create rotorconfig"),
         }
     }
@@ -93,76 +637,138 @@ impl FromIterator<Rotor> for RotorConfig {
 impl TryFrom<(Rotors, char)> for Rotor {
     type Error = Bruh;
 
+    /// Constructs a rotor with ring setting `A`
     fn try_from((variant, position): (Rotors, char)) -> Result<Self, Self::Error> {
-        match variant {
-            Rotors::I => Rotor::new("EKMFLGDQVZNTOWYHXUSPAIBRCJ", &['Q'], position),
-            Rotors::II => Rotor::new("AJDKSIRUXBLHWTMCQGZNPYFVOE", &['E'], position),
-            Rotors::III => Rotor::new("BDFHJLCPRTXVZNYEIWGAKMUSQO", &['V'], position),
-            Rotors::IV => Rotor::new("ESOVPZJAYQUIRHXLNFTGKDCMWB", &['J'], position),
-            Rotors::V => Rotor::new("VZBRGITYUPSDNHLXAWMJQOFECK", &['Z'], position),
-            Rotors::VI => Rotor::new("JPGVOUMFYQBENHZRDKASXLICTW", &['Z', 'M'], position),
-            Rotors::VII => Rotor::new("NZJHGRCXMYSWBOUFAIVLPEKQDT", &['Z', 'M'], position),
-            Rotors::VIII => Rotor::new("FKQHTLXOCBJSPDZRAMEWNIUYGV", &['Z', 'M'], position),
+        Rotor::try_from((variant, position, 'A'))
+    }
+}
+
+impl TryFrom<(Rotors, char, char)> for Rotor {
+    type Error = Bruh;
+
+    fn try_from((variant, position, ring): (Rotors, char, char)) -> Result<Self, Self::Error> {
+        Rotor::try_from((RotorKind::Builtin(variant), position, ring))
+    }
+}
+
+impl<A: Alphabet> TryFrom<(RotorKind, char, char)> for Rotor<A> {
+    type Error = Bruh;
+
+    /// Constructs a rotor from its kind, its starting position and its ring setting
+    /// Fails if the rotor is not wired for the alphabet `A`, for example a `ZRotors` in a machine with letters
+    fn try_from((kind, position, ring): (RotorKind, char, char)) -> Result<Self, Self::Error> {
+        match &kind {
+            RotorKind::Builtin(variant) => {
+                let (wiring, notches) = variant.wiring();
+                let rotor = Rotor::new(kind.clone(), wiring, notches, position, ring)?;
+                match variant.is_greek() {
+                    true => Ok(rotor.stationary()),
+                    false => Ok(rotor),
+                }
+            }
+            RotorKind::Custom(custom) => {
+                let notches: Vec<char> = custom.notches.chars().collect();
+                Rotor::new(kind.clone(), &custom.wiring, &notches, position, ring)
+            }
+            RotorKind::Z(variant) => {
+                let (wiring, notches) = variant.wiring();
+                Rotor::new(kind.clone(), wiring, notches, position, ring)
+            }
         }
     }
 }
 
-impl Rotor {
-    fn new(c: &str, n: &[char], p: char) -> Result<Rotor, Bruh> {
-        let cipher = Cipher::from_str(c).unwrap();
-        let notches = n.iter().map(|p| Position::try_from(*p).unwrap()).collect();
+impl TryFrom<(ZRotors, char, char)> for Rotor<Digits> {
+    type Error = Bruh;
+
+    /// Constructs a rotor of the Enigma Z from its starting position and its ring setting
+    fn try_from((variant, position, ring): (ZRotors, char, char)) -> Result<Self, Self::Error> {
+        Rotor::try_from((RotorKind::Z(variant), position, ring))
+    }
+}
+
+impl<A: Alphabet> Rotor<A> {
+    fn new(kind: RotorKind, c: &str, n: &[char], p: char, r: char) -> Result<Rotor<A>, Bruh> {
+        let cipher =
+            Cipher::from_str(c).with_context(|| format!("Invalid wiring for rotor {kind}"))?;
+        let notches: Notches<A> = n.iter().map(|p| Position::try_from(*p)).try_collect()?;
+        if notches.0.iter().unique().count() != notches.0.len() {
+            return Err(anyhow!("Rotor {kind} has a duplicated notch"));
+        }
         let position = Position::try_from(p)?;
+        let ring = Position::try_from(r)?;
         Ok(Rotor {
-            position: position,
-            cipher: cipher,
-            notches: notches,
+            kind,
+            position,
+            ring,
+            cipher,
+            notches,
+            stationary: false,
+            reversed: false,
         })
     }
 
-    fn encode_at(&self, c: Character, n: usize) -> Character {
-        //println!("{c} {n} {:?}", self.position);
-        let offset: Position = self.position + n;
-        self.cipher.encode(c + offset)
+    pub fn kind(&self) -> &RotorKind {
+        &self.kind
     }
 
-    fn decode_at(&self, c: Character, n: usize) -> Character {
-        let offset: Position = self.position + n;
-        let dec = self.cipher.decode(c);
+    /// Turns the wired core of the rotor around, as the Typex allows. The alphabet ring with the notches stays as it
+    /// is, so only the wiring changes. Reversing a rotor twice restores it
+    pub fn reversed(self) -> Rotor<A> {
+        let contacts: Vec<Character<A>> = Character::all().collect();
+        let mirror = |c: Character<A>| contacts[(A::SIZE - c.get_offset() as usize) % A::SIZE];
+        let wiring: Vec<Character<A>> = contacts
+            .iter()
+            .map(|c| mirror(self.cipher.decode(mirror(*c))))
+            .collect();
+        Rotor {
+            cipher: Cipher::try_from(wiring).unwrap(),
+            reversed: !self.reversed,
+            ..self
+        }
+    }
 
-        //println!("{dec} {offset:?}");
-        dec - offset
+    /// Whether the wired core is inserted the other way around
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
     }
 
-    /// given n revolutions of the current rotor, how many times will the next rotor in the sequence advance?
-    fn get_num_advances(&self, n: usize) -> usize {
-        let r = n / 26;
-        let notches_left = self
-            .notches
-            .0
-            .iter()
-            .filter(|notch| self.position <= **notch)
-            .count();
+    /// Marks the rotor as one that is never moved by the stepping mechanism
+    pub(crate) fn stationary(self) -> Rotor<A> {
+        Rotor {
+            stationary: true,
+            ..self
+        }
+    }
 
-        let final_position = Position::try_from((n % 26) as u8).unwrap();
-        let notches_past = self
-            .notches
-            .0
-            .iter()
-            .filter(|notch| final_position > **notch)
-            .count();
+    /// Offset of the wiring core relative to the entry contacts when the window shows `position`
+    fn offset_at(&self, position: Position<A>) -> Position<A> {
+        position - self.ring
+    }
+
+    fn encode_at(&self, c: Character<A>, position: Position<A>) -> Character<A> {
+        let offset = self.offset_at(position);
+        self.cipher.encode(c + offset) - offset
+    }
 
-        let mut result = (r * self.notches.0.len()) + notches_left;
+    fn decode_at(&self, c: Character<A>, position: Position<A>) -> Character<A> {
+        let offset = self.offset_at(position);
+        self.cipher.decode(c + offset) - offset
+    }
 
-        if r > 0 {
-            result = result + notches_past
-        }
+    /// Whether the rotor is never moved by the stepping mechanism
+    pub fn is_stationary(&self) -> bool {
+        self.stationary
+    }
 
-        result
+    /// Whether the notch engages the next pawl while the window shows `position`
+    pub fn at_notch(&self, position: Position<A>) -> bool {
+        self.notches.0.contains(&position)
     }
 }
 
-impl FromIterator<Position> for Notches {
-    fn from_iter<T: IntoIterator<Item = Position>>(iter: T) -> Self {
+impl<A: Alphabet> FromIterator<Position<A>> for Notches<A> {
+    fn from_iter<T: IntoIterator<Item = Position<A>>>(iter: T) -> Self {
         let mut c = Notches(Vec::new());
         for i in iter {
             c.0.push(i)
@@ -173,18 +779,28 @@ impl FromIterator<Position> for Notches {
 
 #[cfg(test)]
 mod tests {
-    use super::{Rotor, RotorConfig, Rotors};
-    use crate::common::Character;
+    use super::{CustomRotor, Rotor, RotorConfig, RotorKind, Rotors, RECORDED, STRIDE};
+    use crate::{
+        alphabet::Latin,
+        common::{Character, Position},
+        stepping::Drive,
+    };
+    use strum::IntoEnumIterator;
+
+    fn kind() -> RotorKind {
+        RotorKind::Custom(CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", "A", None).unwrap())
+    }
 
     #[test]
     fn rotorconfig_codec() {
-        let _r = || Rotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", &['A'], 'A').unwrap();
+        let _r =
+            || Rotor::<Latin>::new(kind(), "ABCDEFGHIJKLMNOPQRSTUVWXYZ", &['A'], 'A', 'A').unwrap();
 
         let r1 = Rotor::try_from((Rotors::I, 'B')).unwrap();
         let r2 = Rotor::try_from((Rotors::IV, 'N')).unwrap();
         let r3 = Rotor::try_from((Rotors::III, 'X')).unwrap();
 
-        let rc = RotorConfig(vec![r1, r2, r3]);
+        let rc = RotorConfig::from_iter([r1, r2, r3]);
 
         let a = Character::try_from('A').unwrap();
 
@@ -200,24 +816,66 @@ mod tests {
         t(1_000_000);
     }
 
+    fn window(rc: &RotorConfig, n: usize) -> String {
+        // Slowest rotor first, the way the windows read on a real machine
+        rc.positions_at(n)[..rc.rotors.len()]
+            .iter()
+            .rev()
+            .map(|p| char::from(Character::try_from('A').unwrap() + *p))
+            .collect()
+    }
+
     #[test]
-    fn num_advances() {
-        let r = Rotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", &['A'], 'A').unwrap();
+    fn double_step() {
+        // I II III at ADU steps to ADV, AEW, BFX
+        let rc = RotorConfig::try_from([(Rotors::III, 'U'), (Rotors::II, 'D'), (Rotors::I, 'A')])
+            .unwrap();
 
-        {
-            let advances_lt_1_rot = r.get_num_advances(1);
-            assert_eq!(1, advances_lt_1_rot);
-        }
+        assert_eq!(window(&rc, 0), "ADV");
+        assert_eq!(window(&rc, 1), "AEW");
+        assert_eq!(window(&rc, 2), "BFX");
+        assert_eq!(window(&rc, 3), "BFY");
+    }
 
-        {
-            let advances_eq_1_rot = r.get_num_advances(26);
-            assert_eq!(advances_eq_1_rot, 2)
-        }
+    #[test]
+    fn step_before_first_character() {
+        let rc = RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+            .unwrap();
 
-        {
-            let advances_gt_1_rot = r.get_num_advances(53);
-            assert_eq!(advances_gt_1_rot, 4)
-        }
+        assert_eq!(window(&rc, 0), "AAB");
+    }
+
+    #[test]
+    fn two_notches() {
+        // Rotors VI-VIII turn the next rotor over at both M and Z
+        let rc =
+            RotorConfig::try_from([(Rotors::VI, 'L'), (Rotors::VII, 'A'), (Rotors::VIII, 'A')])
+                .unwrap();
+
+        assert_eq!(window(&rc, 0), "AAM");
+        assert_eq!(window(&rc, 1), "ABN");
+        assert_eq!(window(&rc, 13), "ABZ");
+        assert_eq!(window(&rc, 14), "ACA");
+
+        let rc = RotorConfig::try_from([(Rotors::I, 'A'), (Rotors::VII, 'L'), (Rotors::VIII, 'A')])
+            .unwrap();
+        // The middle rotor reaches M after 17 key presses and double steps on the next one
+        assert_eq!(window(&rc, 15), "ALQ");
+        assert_eq!(window(&rc, 16), "AMR");
+        assert_eq!(window(&rc, 17), "BNS");
+    }
+
+    #[test]
+    fn schedule_any_offset() {
+        // Positions looked up from the schedule agree with stepping the rotors one key press at a time
+        let rc = RotorConfig::try_from([(Rotors::VIII, 'C'), (Rotors::V, 'Y'), (Rotors::VI, 'Q')])
+            .unwrap();
+        let mut positions: Vec<Position> = rc.rotors.iter().map(|r| r.position).collect();
+
+        (0..40_000).for_each(|n| {
+            rc.stepping.step(&rc.rotors, &mut positions);
+            assert_eq!(rc.positions_at(n), positions.as_slice())
+        })
     }
 
     #[test]
@@ -260,26 +918,81 @@ mod tests {
         let _ = Rotor::try_from((Rotors::VIII, 'A'));
     }
 
+    #[test]
+    fn construct_beta() {
+        let _ = Rotor::try_from((Rotors::Beta, 'A')).unwrap();
+    }
+
+    #[test]
+    fn construct_gamma() {
+        let _ = Rotor::try_from((Rotors::Gamma, 'A')).unwrap();
+    }
+
+    #[test]
+    fn greek_wheel_never_steps() {
+        let rc = RotorConfig::try_from([
+            (Rotors::I, 'Q'),
+            (Rotors::II, 'E'),
+            (Rotors::III, 'V'),
+            (Rotors::Beta, 'C'),
+        ])
+        .unwrap();
+
+        // Every pawl is engaged, but the Greek wheel has none of its own
+        assert_eq!(window(&rc, 0), "CWFR");
+        (0..20_000).for_each(|n| assert!(window(&rc, n).starts_with('C')));
+    }
+
+    #[test]
+    fn greek_wheel_placement() {
+        let misplaced = RotorConfig::try_from([
+            (Rotors::Gamma, 'A'),
+            (Rotors::II, 'A'),
+            (Rotors::III, 'A'),
+            (Rotors::I, 'A'),
+        ]);
+        assert!(misplaced.is_err());
+
+        let three_rotors =
+            RotorConfig::try_from([(Rotors::I, 'A'), (Rotors::II, 'A'), (Rotors::Beta, 'A')]);
+        assert!(three_rotors.is_err());
+
+        let no_greek_wheel = RotorConfig::try_from([
+            (Rotors::I, 'A'),
+            (Rotors::II, 'A'),
+            (Rotors::III, 'A'),
+            (Rotors::IV, 'A'),
+        ]);
+        assert!(no_greek_wheel.is_err());
+    }
+
+    #[test]
+    fn construct_all_rotors() {
+        Rotors::iter().for_each(|r| {
+            Rotor::try_from((r, 'A')).unwrap();
+        })
+    }
+
     #[test]
     fn construct_all_positions() {
-        ('A'..='Z').into_iter().for_each(|c| {
+        ('A'..='Z').for_each(|c| {
             let _ = Rotor::try_from((Rotors::I, c));
         })
     }
 
     #[test]
     fn codec() {
-        let _r = Rotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", &['A'], 'B').unwrap();
+        let _r =
+            Rotor::<Latin>::new(kind(), "ABCDEFGHIJKLMNOPQRSTUVWXYZ", &['A'], 'B', 'A').unwrap();
 
         //EKMFLGDQVZNTOWYHXUSPAIBRCJ
         let r = Rotor::try_from((Rotors::I, 'B')).unwrap();
-        (0..=1000).into_iter().for_each(|n| {
+        (0..=1000).for_each(|n| {
             ('A'..='Z')
-                .into_iter()
                 .map(|c| Character::try_from(c).unwrap())
                 .for_each(|plaintext| {
-                    let ciphertext = r.encode_at(plaintext, n.into());
-                    let res = r.decode_at(ciphertext, n);
+                    let ciphertext = r.encode_at(plaintext, r.position + n);
+                    let res = r.decode_at(ciphertext, r.position + n);
 
                     println!("{n}: {:?} {plaintext}-{ciphertext}-{res}", r.position);
                     // fix subtraction code
@@ -288,4 +1001,227 @@ mod tests {
                 });
         })
     }
+
+    #[test]
+    fn ring_setting() {
+        // With ring setting B and position A, rotor I wires A to K
+        let r = Rotor::try_from((Rotors::I, 'A', 'B')).unwrap();
+        let a = Character::try_from('A').unwrap();
+        let k = Character::try_from('K').unwrap();
+
+        assert_eq!(r.encode_at(a, r.position), k);
+        assert_eq!(r.decode_at(k, r.position), a);
+    }
+
+    #[test]
+    fn ring_setting_relative_to_position() {
+        // Moving the ring and the position together leaves the wiring where it was
+        let r1 = Rotor::try_from((Rotors::III, 'D', 'B')).unwrap();
+        let r2 = Rotor::try_from((Rotors::III, 'E', 'C')).unwrap();
+
+        ('A'..='Z')
+            .map(|c| Character::try_from(c).unwrap())
+            .for_each(|c| assert_eq!(r1.encode_at(c, r1.position), r2.encode_at(c, r2.position)))
+    }
+
+    #[test]
+    fn ring_setting_codec() {
+        let r = Rotor::try_from((Rotors::VI, 'Q', 'H')).unwrap();
+        (0..=100).for_each(|n| {
+            ('A'..='Z')
+                .map(|c| Character::try_from(c).unwrap())
+                .for_each(|plaintext| {
+                    let ciphertext = r.encode_at(plaintext, r.position + n);
+                    assert_eq!(plaintext, r.decode_at(ciphertext, r.position + n));
+                })
+        })
+    }
+
+    #[test]
+    fn custom_rotor() {
+        // A custom rotor with the wiring and notch of rotor I behaves exactly like it
+        let custom =
+            CustomRotor::new("ekmflgdqvzntowyhxuspaibrcj", "q", Some("Copy of I")).unwrap();
+        let custom = RotorConfig::try_from(vec![
+            (RotorKind::Custom(custom), 'P', 'C'),
+            (Rotors::II.into(), 'E', 'A'),
+            (Rotors::III.into(), 'V', 'A'),
+        ])
+        .unwrap();
+        let builtin = RotorConfig::try_from([
+            (Rotors::I, 'P', 'C'),
+            (Rotors::II, 'E', 'A'),
+            (Rotors::III, 'V', 'A'),
+        ])
+        .unwrap();
+
+        (0..1000).for_each(|n| {
+            assert_eq!(window(&custom, n), window(&builtin, n));
+            ('A'..='Z')
+                .map(|c| Character::try_from(c).unwrap())
+                .for_each(|c| assert_eq!(custom.encode_at(c, n), builtin.encode_at(c, n)))
+        })
+    }
+
+    #[test]
+    fn custom_rotor_many_notches() {
+        let custom = CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", "ACEGIKMOQSUWY", None).unwrap();
+        let rc = RotorConfig::try_from(vec![
+            (RotorKind::Custom(custom), 'A', 'A'),
+            (Rotors::II.into(), 'A', 'A'),
+            (Rotors::III.into(), 'A', 'A'),
+        ])
+        .unwrap();
+
+        // Every other key press turns the middle rotor over
+        assert_eq!(window(&rc, 0), "ABB");
+        assert_eq!(window(&rc, 1), "ABC");
+        assert_eq!(window(&rc, 3), "ACE");
+    }
+
+    #[test]
+    fn custom_rotor_validation() {
+        assert!(CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXY", "A", None).is_err());
+        assert!(CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYY", "A", None).is_err());
+        assert!(CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", "AA", None).is_err());
+        assert!(CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", "1", None).is_err());
+        assert!(CustomRotor::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ", "", None).is_ok());
+    }
+
+    #[test]
+    fn driven_reflector() {
+        // The reflector is pushed by the pawl after the slow rotor, the same way the slow rotor is double stepped
+        let rc = RotorConfig::try_from([(Rotors::III, 'U'), (Rotors::II, 'D'), (Rotors::I, 'P')])
+            .unwrap()
+            .drive_reflector(Position::try_from('C').unwrap());
+        let position = |n| {
+            char::from(Character::try_from('A').unwrap() + rc.reflector_position_at(n).unwrap())
+        };
+
+        assert_eq!(window(&rc, 0), "PDV");
+        assert_eq!(position(0), 'C');
+        assert_eq!(window(&rc, 1), "PEW");
+        assert_eq!(window(&rc, 2), "QFX");
+        assert_eq!(position(2), 'C');
+        assert_eq!(window(&rc, 3), "RFY");
+        assert_eq!(position(3), 'D');
+    }
+
+    #[test]
+    fn five_notches() {
+        // TI turns the next rotor over at E, K, Q, W and Z
+        let rc =
+            RotorConfig::try_from([(Rotors::TI, 'D'), (Rotors::TII, 'A'), (Rotors::TIII, 'A')])
+                .unwrap();
+
+        assert_eq!(window(&rc, 0), "AAE");
+        assert_eq!(window(&rc, 1), "ABF");
+        assert_eq!(window(&rc, 6), "ABK");
+        assert_eq!(window(&rc, 7), "ACL");
+        assert_eq!(window(&rc, 13), "ADR");
+        assert_eq!(window(&rc, 19), "AEX");
+        assert_eq!(window(&rc, 22), "AFA");
+        // TII has a notch at F and double steps
+        assert_eq!(window(&rc, 23), "BGB");
+        assert_eq!(window(&rc, 27), "BHF");
+    }
+
+    #[test]
+    fn cog_wheels() {
+        // The middle rotor does not double step, it only moves when the fast rotor passes its notch
+        let rc = RotorConfig::try_from([(Rotors::III, 'U'), (Rotors::II, 'D'), (Rotors::I, 'A')])
+            .unwrap()
+            .with_drive(Drive::CogWheels);
+
+        assert_eq!(window(&rc, 0), "ADV");
+        assert_eq!(window(&rc, 1), "AEW");
+        assert_eq!(window(&rc, 2), "AEX");
+
+        // A carry into a rotor at its notch moves the next rotor as well
+        let rc = RotorConfig::try_from([(Rotors::III, 'V'), (Rotors::II, 'E'), (Rotors::I, 'A')])
+            .unwrap()
+            .with_drive(Drive::CogWheels);
+        assert_eq!(window(&rc, 0), "BFW");
+    }
+
+    #[test]
+    fn cog_wheels_many_notches() {
+        // GIII carries at U, W and X but not at V
+        let rc =
+            RotorConfig::try_from([(Rotors::GIII, 'T'), (Rotors::GII, 'I'), (Rotors::GI, 'D')])
+                .unwrap()
+                .with_drive(Drive::CogWheels)
+                .drive_reflector(Position::try_from('A').unwrap());
+        let position = |rc: &RotorConfig, n| {
+            char::from(Character::try_from('A').unwrap() + rc.reflector_position_at(n).unwrap())
+        };
+
+        assert_eq!(window(&rc, 0), "DIU");
+        assert_eq!(window(&rc, 1), "DJV");
+        assert_eq!(window(&rc, 2), "DJW");
+        assert_eq!(window(&rc, 3), "DKX");
+        assert_eq!(window(&rc, 4), "ELY");
+        assert_eq!(position(&rc, 4), 'A');
+
+        // Every rotor at a notch carries all the way to the reflector
+        let rc =
+            RotorConfig::try_from([(Rotors::GIII, 'U'), (Rotors::GII, 'S'), (Rotors::GI, 'S')])
+                .unwrap()
+                .with_drive(Drive::CogWheels)
+                .drive_reflector(Position::try_from('A').unwrap());
+        assert_eq!(window(&rc, 0), "TTV");
+        assert_eq!(position(&rc, 0), 'B');
+    }
+
+    #[test]
+    fn long_cycle() {
+        // Six rotors counting like an odometer take 26^6 key presses to repeat, far more than are recorded
+        let rotors = (0..6)
+            .map(|_| Rotor::try_from((kind(), 'A', 'A')).unwrap())
+            .collect();
+        let rc = RotorConfig::from_rotors(rotors)
+            .unwrap()
+            .with_drive(Drive::Odometer);
+        let counter = |presses: usize| -> Vec<Position> {
+            (0..6)
+                .map(|i| Position::try_from((presses / 26usize.pow(i) % 26) as u8).unwrap())
+                .collect()
+        };
+
+        for n in [
+            0,
+            RECORDED - 1,
+            RECORDED,
+            RECORDED + 3 * STRIDE + 5,
+            2 * RECORDED,
+        ] {
+            assert_eq!(rc.positions_at(n).to_vec(), counter(n + 1));
+        }
+        assert_eq!(rc.positions_after(RECORDED + 1), counter(RECORDED + 1));
+    }
+
+    #[test]
+    fn fixed_reflector() {
+        let rc = RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+            .unwrap();
+        assert_eq!(rc.reflector_position_at(0), None);
+    }
+
+    #[test]
+    fn reversed() {
+        let r = Rotor::try_from((Rotors::I, 'A')).unwrap().reversed();
+        let a = Character::try_from('A').unwrap();
+        let p = Position::try_from(0).unwrap();
+
+        // I wires U to A, so the reversed core wires A to the mirror image of U
+        assert!(r.is_reversed());
+        assert_eq!(r.encode_at(a, p), Character::try_from('G').unwrap());
+
+        let r = r.reversed();
+        let original = Rotor::try_from((Rotors::I, 'A')).unwrap();
+        assert!(!r.is_reversed());
+        ('A'..='Z')
+            .map(|c| Character::try_from(c).unwrap())
+            .for_each(|c| assert_eq!(r.encode_at(c, p), original.encode_at(c, p)));
+    }
 }
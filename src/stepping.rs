@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+use crate::{
+    alphabet::{Alphabet, Latin},
+    common::Position,
+    rotor::Rotor,
+};
+
+/// How the rotors are moved from one key press to the next
+///
+/// `rotors` are ordered from the entry side, like in `RotorConfig`. `positions` holds the window position of every
+/// rotor, and one more position after them if the reflector is driven by the rotors. The reflector has no notches.
+/// Rotors that are marked stationary, such as the Greek wheels and the stators of the Typex, have no pawl or cog and
+/// are expected to keep their position
+pub trait Stepping<A: Alphabet = Latin>: Send + Sync {
+    /// Advances `positions` by a single key press
+    ///
+    /// Must be pure and deterministic: the new positions may only depend on `rotors` and `positions`. The positions
+    /// are worked out ahead of time and looked up out of order, so a stepping that keeps state of its own or
+    /// depends on anything else would encipher differently from the machine it describes
+    fn step(&self, rotors: &[Rotor<A>], positions: &mut [Position<A>]);
+
+    /// The member of `Drive` this stepping belongs to, if any
+    fn drive(&self) -> Option<Drive> {
+        None
+    }
+}
+
+/// The built in ways of moving the rotors, to choose one of them in a configuration or a machine model
+#[derive(EnumIter, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Drive {
+    /// Pawls and ratchets, which double step the middle rotor, see `Ratchet`
+    #[default]
+    #[strum(to_string = "pawls")]
+    Pawls,
+    /// Cog wheels as on the Enigma G, see `CogWheels`
+    #[strum(to_string = "cog wheels")]
+    CogWheels,
+    /// A counter that ignores the notches, see `Odometer`
+    #[strum(to_string = "odometer")]
+    Odometer,
+    /// No rotor ever moves, see `NeverStep`
+    #[strum(to_string = "never")]
+    Never,
+}
+
+impl Drive {
+    pub fn stepping<A: Alphabet>(&self) -> Box<dyn Stepping<A>> {
+        match self {
+            Drive::Pawls => Box::new(Ratchet),
+            Drive::CogWheels => Box::new(CogWheels),
+            Drive::Odometer => Box::new(Odometer),
+            Drive::Never => Box::new(NeverStep),
+        }
+    }
+}
+
+/// Rotors step the way the pawls of the Enigma move them: before each key press is enciphered the fast rotor advances,
+/// and every other rotor advances when the rotor before it shows a notch. A rotor that is pushed by a pawl while at
+/// its own notch carries its neighbour along, which produces the double step of the middle rotor
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ratchet;
+
+/// The cog wheels of the Enigma G. The fast rotor always steps, and every rotor that moves away from a notch carries
+/// the next one along. There is no double step
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CogWheels;
+
+/// A plain counter. The fast rotor always steps, and every rotor carries the next one along once per revolution, when
+/// it moves on from the last symbol of the alphabet to the first. Notches are ignored
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Odometer;
+
+/// No rotor ever moves, as if every rotor were a Greek wheel. The machine becomes a fixed substitution
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NeverStep;
+
+/// Indices of the rotors that can be moved, and of a driven reflector
+fn moving<A: Alphabet>(
+    rotors: &[Rotor<A>],
+    width: usize,
+) -> impl DoubleEndedIterator<Item = usize> + '_ {
+    (0..width).filter(|i| rotors.get(*i).is_none_or(|r| !r.is_stationary()))
+}
+
+impl<A: Alphabet> Stepping<A> for Ratchet {
+    fn step(&self, rotors: &[Rotor<A>], positions: &mut [Position<A>]) {
+        let at_notch =
+            |p: &[Position<A>], i: usize| rotors.get(i).is_some_and(|r| r.at_notch(p[i]));
+        let mut moving = moving(rotors, positions.len()).rev().peekable();
+
+        // The fast rotor always steps. Each pawl after it engages when the rotor on its right shows a notch,
+        // and then pushes both of the rotors it sits between. Working from the slow end means every notch is
+        // read before the rotor carrying it has moved
+        let mut has_pawl = false;
+        while let Some(i) = moving.next() {
+            let pushed = match moving.peek() {
+                Some(faster) => at_notch(positions, *faster),
+                None => true,
+            };
+            if pushed || (has_pawl && at_notch(positions, i)) {
+                positions[i].advance();
+            }
+            has_pawl = true;
+        }
+    }
+
+    fn drive(&self) -> Option<Drive> {
+        Some(Drive::Pawls)
+    }
+}
+
+impl<A: Alphabet> Stepping<A> for CogWheels {
+    fn step(&self, rotors: &[Rotor<A>], positions: &mut [Position<A>]) {
+        for i in moving(rotors, positions.len()) {
+            let carry = rotors.get(i).is_some_and(|r| r.at_notch(positions[i]));
+            positions[i].advance();
+            if !carry {
+                break;
+            }
+        }
+    }
+
+    fn drive(&self) -> Option<Drive> {
+        Some(Drive::CogWheels)
+    }
+}
+
+impl<A: Alphabet> Stepping<A> for Odometer {
+    fn step(&self, rotors: &[Rotor<A>], positions: &mut [Position<A>]) {
+        let last = Position::try_from((A::SIZE - 1) as u8).unwrap();
+        for i in moving(rotors, positions.len()) {
+            let carry = positions[i] == last;
+            positions[i].advance();
+            if !carry {
+                break;
+            }
+        }
+    }
+
+    fn drive(&self) -> Option<Drive> {
+        Some(Drive::Odometer)
+    }
+}
+
+impl<A: Alphabet> Stepping<A> for NeverStep {
+    fn step(&self, _rotors: &[Rotor<A>], _positions: &mut [Position<A>]) {}
+
+    fn drive(&self) -> Option<Drive> {
+        Some(Drive::Never)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CogWheels, NeverStep, Odometer, Ratchet, Stepping};
+    use crate::{
+        common::{Character, Position},
+        enigma::Enigma,
+        reflector::Reflectors,
+        rotor::{Rotor, RotorConfig, Rotors},
+    };
+
+    fn rotors() -> Vec<Rotor> {
+        [Rotors::III, Rotors::II, Rotors::I]
+            .into_iter()
+            .map(|r| Rotor::try_from((r, 'A')).unwrap())
+            .collect()
+    }
+
+    /// Window letters after each of `presses` key presses, starting from `start`, slow rotor first
+    fn windows(stepping: &dyn Stepping, start: &str, presses: usize) -> Vec<String> {
+        let rotors = rotors();
+        let mut positions: Vec<Position> = start
+            .chars()
+            .rev()
+            .map(|c| Position::try_from(c).unwrap())
+            .collect();
+        (0..presses)
+            .map(|_| {
+                stepping.step(&rotors, &mut positions);
+                positions
+                    .iter()
+                    .rev()
+                    .map(|p| char::from(Character::try_from('A').unwrap() + *p))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ratchet() {
+        assert_eq!(windows(&Ratchet, "ADU", 3), ["ADV", "AEW", "BFX"]);
+    }
+
+    #[test]
+    fn cog_wheels() {
+        assert_eq!(windows(&CogWheels, "ADU", 3), ["ADV", "AEW", "AEX"]);
+    }
+
+    #[test]
+    fn odometer() {
+        assert_eq!(windows(&Odometer, "AZY", 3), ["AZZ", "BAA", "BAB"]);
+        assert_eq!(windows(&Odometer, "ADU", 2), ["ADV", "ADW"]);
+    }
+
+    #[test]
+    fn never_step() {
+        assert_eq!(windows(&NeverStep, "ADU", 2), ["ADU", "ADU"]);
+    }
+
+    #[test]
+    fn user_defined() {
+        // Every rotor steps on every key press
+        struct Lockstep;
+
+        impl Stepping for Lockstep {
+            fn step(&self, _rotors: &[Rotor], positions: &mut [Position]) {
+                positions.iter_mut().for_each(|p| p.advance())
+            }
+        }
+
+        assert_eq!(windows(&Lockstep, "ADU", 2), ["BEV", "CFW"]);
+
+        let rotors =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap()
+                .with_stepping(Lockstep);
+        assert_eq!(rotors.drive(), None);
+        let e = Enigma::without_plugboard(rotors, Reflectors::B);
+        assert_eq!(e.encode(&e.encode("LOCKSTEP")), "LOCKSTEP");
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+
+use crate::{
+    alphabet::{Alphabet, Latin},
+    enigma::Enigma,
+};
+
+/// Enciphers everything written to it and writes the ciphertext to `inner`
+///
+/// Text can be written in pieces of any size, even ones that split a character. Only the bytes of an unfinished
+/// character are held back, so enciphering takes constant memory however long the message is. Every piece is
+/// enciphered from where the previous one stopped, just like `Enigma::encode` enciphers the whole message.
+/// Call `finish` after the last write to check that the message did not end in the middle of a character
+pub struct EncodeWriter<'a, W: Write, A: Alphabet = Latin> {
+    enigma: &'a Enigma<A>,
+    inner: W,
+    presses: usize,
+    pending: Vec<u8>,
+}
+
+impl<'a, W: Write, A: Alphabet> EncodeWriter<'a, W, A> {
+    pub fn new(enigma: &'a Enigma<A>, inner: W) -> EncodeWriter<'a, W, A> {
+        EncodeWriter {
+            enigma,
+            inner,
+            presses: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Flushes the ciphertext and returns the inner writer
+    /// Fails if the text written so far ends in the middle of a character
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The text ended in the middle of a character",
+            ));
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, A: Alphabet> Write for EncodeWriter<'_, W, A> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s,
+            Err(e) => match e.error_len() {
+                Some(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Only UTF-8 text can be enciphered",
+                    ))
+                }
+                // The rest is the start of a character that the next write completes
+                None => std::str::from_utf8(&self.pending[..e.valid_up_to()]).unwrap(),
+            },
+        };
+
+        let (ciphertext, presses) = self
+            .enigma
+            .encode_from(complete, self.presses)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let len = complete.len();
+        self.inner.write_all(ciphertext.as_bytes())?;
+        self.presses = presses;
+        self.pending.drain(..len);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::EncodeWriter;
+    use crate::{
+        enigma::Enigma,
+        reflector::Reflectors,
+        rotor::{RotorConfig, Rotors},
+    };
+
+    fn enigma() -> Enigma {
+        let rotors =
+            RotorConfig::try_from([(Rotors::III, 'Q'), (Rotors::II, 'D'), (Rotors::I, 'A')])
+                .unwrap();
+        Enigma::without_plugboard(rotors, Reflectors::B)
+    }
+
+    const PLAINTEXT: &str = "DER FÜHRER IST TOT. DER KAMPF GEHT WEITER";
+
+    #[test]
+    fn pieces() {
+        let e = enigma();
+        // Every split point, including the middle of the two bytes of Ü
+        (0..=PLAINTEXT.len()).for_each(|split| {
+            let (first, second) = PLAINTEXT.as_bytes().split_at(split);
+            let mut encoder = EncodeWriter::new(&e, Vec::new());
+            encoder.write_all(first).unwrap();
+            encoder.write_all(second).unwrap();
+            let ciphertext = String::from_utf8(encoder.finish().unwrap()).unwrap();
+
+            assert_eq!(ciphertext, e.encode(PLAINTEXT));
+        })
+    }
+
+    #[test]
+    fn encode_stream() {
+        let e = enigma();
+        let plaintext = PLAINTEXT.repeat(1000);
+        let mut ciphertext = Vec::new();
+
+        let read = e
+            .encode_stream(Cursor::new(&plaintext), &mut ciphertext)
+            .unwrap();
+        assert_eq!(read, plaintext.len() as u64);
+        assert_eq!(String::from_utf8(ciphertext).unwrap(), e.encode(&plaintext));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let e = enigma();
+        let mut encoder = EncodeWriter::new(&e, Vec::new());
+        assert!(encoder.write_all(&[b'A', 0xFF]).is_err());
+
+        let mut encoder = EncodeWriter::new(&e, Vec::new());
+        encoder.write_all(&"Ü".as_bytes()[..1]).unwrap();
+        assert!(encoder.finish().is_err());
+    }
+}
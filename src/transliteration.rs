@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Rewrites plaintext into letters the machine can encipher before it is enciphered
+#[derive(
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Default,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Transliteration {
+    /// The text is enciphered as it is
+    #[default]
+    None,
+    /// German text is written the way operators prepared it: `Ä`, `Ö` and `Ü` become `AE`, `OE` and `UE`, `ß`
+    /// becomes `SS` and every digit is spelled out, so `17` becomes `EINSSIEBEN`
+    German,
+}
+
+impl Transliteration {
+    pub fn apply<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        match self {
+            Transliteration::None => Cow::Borrowed(s),
+            Transliteration::German => Cow::Owned(s.chars().map(german).collect()),
+        }
+    }
+}
+
+/// The German spelling of each digit, `SPELLED_DIGITS[7]` is `SIEBEN`
+pub(crate) const SPELLED_DIGITS: [&str; 10] = [
+    "NULL", "EINS", "ZWEI", "DREI", "VIER", "FUENF", "SECHS", "SIEBEN", "ACHT", "NEUN",
+];
+
+/// The German spelling of a single character
+fn german(c: char) -> Cow<'static, str> {
+    let replacement = match c {
+        'Ä' => "AE",
+        'Ö' => "OE",
+        'Ü' => "UE",
+        'ä' => "ae",
+        'ö' => "oe",
+        'ü' => "ue",
+        'ß' => "ss",
+        'ẞ' => "SS",
+        '0'..='9' => SPELLED_DIGITS[c as usize - '0' as usize],
+        _ => return Cow::Owned(c.to_string()),
+    };
+    Cow::Borrowed(replacement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transliteration;
+
+    #[test]
+    fn german() {
+        assert_eq!(
+            Transliteration::German.apply("Größe: 17 Fässer"),
+            "Groesse: EINSSIEBEN Faesser"
+        );
+        assert_eq!(Transliteration::German.apply("ÜBER ÖL"), "UEBER OEL");
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(Transliteration::None.apply("Größe 17"), "Größe 17");
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::anyhow;
+use bruh_moment::Bruh;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::{
+    enigma::{EncodeError, Enigma, NonAlphabetic},
+    plugboard::Plugboard,
+    reflector::Reflector,
+    rotor::{CustomRotor, Rotor, RotorConfig, RotorKind},
+};
+
+/// The letters on the alphabet ring of every example rotor at which the next rotor is turned over
+const NOTCHES: &str = "BFHNQUW";
+
+/// The British Typex
+///
+/// Five rotors sit between the entry and the reflector. The two next to the entry are stators, which are set by hand
+/// and never move. The other three step like the rotors of the Enigma, with pawls that engage the many notches on
+/// their alphabet rings. The wired core of any rotor can be inserted reversed, see `Rotor::reversed`.
+/// Early marks had no plugboard, later marks plug the keys before the entry
+///
+/// The wartime service wirings of the Typex are not public. `TypexRotors` and `TypexReflectors` hold the example wirings
+/// that public simulators such as CyberChef ship with, other wirings are supplied with `CustomRotor` and
+/// `Reflector::from_wiring`
+pub struct Typex {
+    machine: Enigma,
+}
+
+/// The example rotors of public Typex simulators, which all carry notches at `BFHNQUW`
+#[derive(
+    EnumString, EnumIter, Hash, PartialEq, Eq, Clone, Copy, Display, Debug, Serialize, Deserialize,
+)]
+pub enum TypexRotors {
+    Example1,
+    Example2,
+    Example3,
+    Example4,
+    Example5,
+    Example6,
+    Example7,
+    Example8,
+}
+
+impl TypexRotors {
+    fn wiring(&self) -> &'static str {
+        match self {
+            TypexRotors::Example1 => "MCYLPQUVRXGSAOWNBJEZDTFKHI",
+            TypexRotors::Example2 => "KHWENRCBISXJQGOFMAPVYZDLTU",
+            TypexRotors::Example3 => "BYPDZMGIKQCUSATREHOJNLFWXV",
+            TypexRotors::Example4 => "ZANJCGDLVHIXOBRPMSWQUKFYET",
+            TypexRotors::Example5 => "QXBGUTOVFCZPJIHSWERYNDAMLK",
+            TypexRotors::Example6 => "BDCNWUEIQVFTSXALOGZJYMHKPR",
+            TypexRotors::Example7 => "WJUKEIABMSGFTQZVCNPHORDXYL",
+            TypexRotors::Example8 => "TNVCZXDIPFWQKHSJMAOYLEURGB",
+        }
+    }
+
+    /// The rotor turned to `position` with its alphabet ring set to `ring`
+    pub fn rotor(&self, position: char, ring: char) -> Result<Rotor, Bruh> {
+        let custom = CustomRotor::new(self.wiring(), NOTCHES, Some(&self.to_string()))?;
+        Rotor::try_from((RotorKind::Custom(custom), position, ring))
+    }
+}
+
+/// The example reflector of public Typex simulators
+#[derive(
+    EnumIter, EnumString, Display, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+pub enum TypexReflectors {
+    Example,
+}
+
+impl From<TypexReflectors> for Reflector {
+    fn from(r: TypexReflectors) -> Self {
+        match r {
+            TypexReflectors::Example => {
+                Reflector::from_wiring("NCBKIGFMEXDUHARYWOTSLZQJPV").unwrap()
+            }
+        }
+    }
+}
+
+impl Typex {
+    /// `rotors` are listed from the entry side, so the first two are the stators and the third is the fast rotor
+    /// `plugboard` is `None` for the early marks without a plugboard
+    /// Fails if a rotor is used twice
+    pub fn new(
+        rotors: [Rotor; 5],
+        plugboard: Option<Plugboard>,
+        reflector: impl Into<Reflector>,
+    ) -> Result<Typex, Bruh> {
+        if rotors.iter().map(|r| r.kind()).unique().count() != rotors.len() {
+            return Err(anyhow!("Every rotor of the Typex must be different"));
+        }
+        let [stator_1, stator_2, fast, middle, slow] = rotors;
+        let rotors = RotorConfig::from_rotors(vec![
+            stator_1.stationary(),
+            stator_2.stationary(),
+            fast,
+            middle,
+            slow,
+        ])?;
+
+        let machine = match plugboard {
+            Some(plugboard) => Enigma::new(rotors, plugboard, reflector),
+            None => Enigma::without_plugboard(rotors, reflector),
+        };
+        Ok(Typex { machine })
+    }
+
+    /// Handles characters outside the alphabet with `policy` instead of passing them through
+    pub fn with_non_alphabetic(self, policy: NonAlphabetic) -> Typex {
+        Typex {
+            machine: self.machine.with_non_alphabetic(policy),
+        }
+    }
+
+    /// Enciphers every letter in `s`, see `Enigma::encode`
+    pub fn encode(&self, s: &str) -> String {
+        self.machine.encode(s)
+    }
+
+    /// Like `encode`, but fails instead of panicking, see `Enigma::try_encode`
+    pub fn try_encode(&self, s: &str) -> Result<String, EncodeError> {
+        self.machine.try_encode(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Typex, TypexReflectors, TypexRotors};
+    use crate::{
+        enigma::{Enigma, NonAlphabetic},
+        machine::Machine,
+        plugboard::{Plug, Plugboard, Plugs},
+        reflector::{Reflector, Reflectors},
+        rotor::{CustomRotor, Rotor, RotorConfig, RotorKind, Rotors},
+    };
+
+    fn typex(reversed: bool, plugboard: Option<Plugboard>) -> Typex {
+        let middle = TypexRotors::Example4.rotor('F', 'A').unwrap();
+        let middle = match reversed {
+            true => middle.reversed(),
+            false => middle,
+        };
+        let rotors = [
+            TypexRotors::Example1.rotor('Q', 'A').unwrap(),
+            TypexRotors::Example2.rotor('D', 'A').unwrap(),
+            TypexRotors::Example3.rotor('X', 'A').unwrap(),
+            middle,
+            TypexRotors::Example5.rotor('L', 'A').unwrap(),
+        ];
+        Typex::new(rotors, plugboard, TypexReflectors::Example).unwrap()
+    }
+
+    #[test]
+    fn known_answer() {
+        // Worked out with a separate simulation written from the machine's description rather than by this crate, it
+        // uses the same example wirings so it checks the stepping and the path through the rotors, not the wirings
+        let t = typex(false, None);
+
+        assert_eq!(t.encode("AAAAAAAAAA"), "HRCPHHTSYF");
+        assert_eq!(
+            t.encode("THEQUICKBROWNFOXJUMPEDOVERTHELAZYDOG"),
+            "JMLCSPECTXBCDDIPDWTLREZMQVQRKOPSCUTM"
+        );
+    }
+
+    #[test]
+    fn codec() {
+        let plaintext = "THE QUICK BROWN FOX JUMPED OVER THE LAZY DOG";
+        let t = typex(false, None);
+
+        let ciphertext = t.encode(plaintext);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(t.encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn reversed_rotor() {
+        let plaintext = "THE QUICK BROWN FOX JUMPED OVER THE LAZY DOG";
+        let ciphertext = typex(true, None).encode(plaintext);
+
+        assert_ne!(ciphertext, typex(false, None).encode(plaintext));
+        assert_eq!(typex(true, None).encode(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn plugboard() {
+        let plugboard = || {
+            let plugs: Vec<Plug> = [('A', 'Q'), ('T', 'K'), ('E', 'O')]
+                .into_iter()
+                .map(|(a, b)| {
+                    let pair = (a.try_into().unwrap(), b.try_into().unwrap());
+                    Plug::try_from(pair).unwrap()
+                })
+                .collect();
+            Plugboard::try_from(Plugs::try_from(plugs).unwrap()).unwrap()
+        };
+        let plaintext = "ATTACK AT ONCE";
+
+        let ciphertext = typex(false, Some(plugboard())).encode(plaintext);
+        assert_ne!(ciphertext, typex(false, None).encode(plaintext));
+        assert_eq!(
+            typex(false, Some(plugboard())).encode(&ciphertext),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn custom_wiring() {
+        // Wirings that are not built in are supplied as custom rotors and reflectors
+        let rotor = |wiring: &str, position: char| {
+            let custom = CustomRotor::new(wiring, "GMTZ", None).unwrap();
+            Rotor::try_from((RotorKind::Custom(custom), position, 'A')).unwrap()
+        };
+        let rotors = [
+            rotor("EKMFLGDQVZNTOWYHXUSPAIBRCJ", 'Q'),
+            rotor("AJDKSIRUXBLHWTMCQGZNPYFVOE", 'D'),
+            rotor("BDFHJLCPRTXVZNYEIWGAKMUSQO", 'X'),
+            rotor("ESOVPZJAYQUIRHXLNFTGKDCMWB", 'F'),
+            rotor("VZBRGITYUPSDNHLXAWMJQOFECK", 'L'),
+        ];
+        let reflector = Reflector::from_wiring("YRUHQSLDPXNGOKMIEBFZCWVJAT").unwrap();
+        let t = Typex::new(rotors, None, reflector).unwrap();
+
+        let ciphertext = t.encode("TYPEX");
+        assert_ne!(ciphertext, "TYPEX");
+        assert_eq!(t.encode(&ciphertext), "TYPEX");
+    }
+
+    #[test]
+    fn duplicate_rotor() {
+        let rotors = [
+            TypexRotors::Example1.rotor('A', 'A').unwrap(),
+            TypexRotors::Example2.rotor('A', 'A').unwrap(),
+            TypexRotors::Example3.rotor('A', 'A').unwrap(),
+            TypexRotors::Example1.rotor('B', 'A').unwrap(),
+            TypexRotors::Example5.rotor('A', 'A').unwrap(),
+        ];
+        assert!(Typex::new(rotors, None, Reflectors::B).is_err());
+    }
+
+    #[test]
+    fn machine() {
+        let enigma =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        let machines: Vec<Box<dyn Machine>> = vec![
+            Box::new(Enigma::without_plugboard(enigma, Reflectors::B)),
+            Box::new(typex(false, None)),
+        ];
+
+        machines
+            .iter()
+            .for_each(|m| assert_eq!(m.encode(&m.encode("TYPEX")), "TYPEX"));
+    }
+
+    #[test]
+    fn machine_reject() {
+        let enigma =
+            RotorConfig::try_from([(Rotors::III, 'A'), (Rotors::II, 'A'), (Rotors::I, 'A')])
+                .unwrap();
+        let machines: Vec<Box<dyn Machine>> = vec![
+            Box::new(
+                Enigma::without_plugboard(enigma, Reflectors::B)
+                    .with_non_alphabetic(NonAlphabetic::Reject),
+            ),
+            Box::new(typex(false, None).with_non_alphabetic(NonAlphabetic::Reject)),
+        ];
+
+        machines.iter().for_each(|m| {
+            assert!(m.try_encode("TYPEX").is_ok());
+            assert!(m.try_encode("TYPE X").is_err());
+        });
+    }
+}
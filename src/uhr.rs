@@ -0,0 +1,188 @@
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    alphabet::{Alphabet, Latin},
+    cipher::{Cipher, Decode, Encode},
+    common::Character,
+};
+
+/// Number of contacts on the switch, and of positions the dial can be turned to
+pub const UHR_POSITIONS: u8 = 40;
+
+/// Wiring of the switch, contact `i` on the side of the `a` plugs connects to contact `UHR_WIRING[i]` on the side of
+/// the `b` plugs while the dial is at `00`
+const UHR_WIRING: [u8; 40] = [
+    6, 31, 4, 29, 18, 39, 16, 25, 30, 23, 28, 1, 38, 11, 36, 37, 26, 27, 24, 21, 14, 3, 12, 17, 2,
+    7, 0, 33, 10, 35, 8, 5, 22, 19, 20, 13, 34, 15, 32, 9,
+];
+
+/// The Enigma Uhr is a switch box that takes the place of the plug cables
+///
+/// Ten pairs of plugs are numbered `1a`-`10a` and `1b`-`10b`. Each plug has a thick pin on the keyboard side and a
+/// thin pin on the entry wheel side, and every pin ends at one of the 40 contacts of the switch. At dial position `00`
+/// the switch connects `1a` with `1b` and so on, exactly like ten plug cables. At any other position the pairs are
+/// scrambled and the substitution is no longer reciprocal
+pub struct Uhr<A: Alphabet = Latin> {
+    pairs: Vec<(Character<A>, Character<A>)>,
+    dial: u8,
+    cipher: Cipher<A>,
+}
+
+#[derive(Error, Debug)]
+pub enum UhrError {
+    #[error("Recieved {0} pairs, The Uhr takes exactly 10 pairs of plugs")]
+    PairCount(usize),
+    #[error("Can not plug multiple plugs into the same socket")]
+    Mapping,
+    #[error("Recieved {0}, The dial of the Uhr only goes from 0 to 39")]
+    Dial(u8),
+}
+
+impl<A: Alphabet> Uhr<A> {
+    /// `pairs` lists the socket of the `a` plug and of the `b` plug of each pair, starting with `1a` and `1b`
+    pub fn new(pairs: &[(Character<A>, Character<A>)], dial: u8) -> Result<Uhr<A>, UhrError> {
+        if pairs.len() != 10 {
+            return Err(UhrError::PairCount(pairs.len()));
+        }
+        if pairs.iter().flat_map(|(a, b)| [a, b]).unique().count() != 20 {
+            return Err(UhrError::Mapping);
+        }
+        if dial >= UHR_POSITIONS {
+            return Err(UhrError::Dial(dial));
+        }
+
+        let cipher = Uhr::wire(pairs, dial);
+        Ok(Uhr {
+            pairs: pairs.to_vec(),
+            dial,
+            cipher,
+        })
+    }
+
+    /// Turns the dial to `dial`
+    pub fn with_dial(self, dial: u8) -> Result<Uhr<A>, UhrError> {
+        Uhr::new(&self.pairs, dial)
+    }
+
+    pub fn dial(&self) -> u8 {
+        self.dial
+    }
+
+    /// Works out the substitution from the keyboard side to the entry wheel side
+    ///
+    /// The thick pin of plug `na` sits on contact `4n` and its thin pin on `4n + 3` of the `a` side. The pins of plug
+    /// `nb` sit on the contacts the switch connects those to at position `00`
+    fn wire(pairs: &[(Character<A>, Character<A>)], dial: u8) -> Cipher<A> {
+        let n = UHR_POSITIONS as usize;
+        let dial = dial as usize;
+        let mut inverse = [0; 40];
+        UHR_WIRING
+            .iter()
+            .enumerate()
+            .for_each(|(i, w)| inverse[*w as usize] = i);
+        // The contact on the other side a contact is connected to, with the switch turned by `dial`
+        let to_b = |a: usize| (UHR_WIRING[(a + dial) % n] as usize + n - dial) % n;
+        let to_a = |b: usize| (inverse[(b + dial) % n] + n - dial) % n;
+
+        let a_thin = |contact: usize| pairs[(contact - 3) / 4].0;
+        let b_thin = |contact: usize| {
+            let plug = (0..10)
+                .find(|p| UHR_WIRING[4 * p] as usize == contact)
+                .unwrap();
+            pairs[plug].1
+        };
+
+        let mut wiring: Vec<Character<A>> = Character::all().collect();
+        pairs.iter().enumerate().for_each(|(plug, (a, b))| {
+            wiring[a.get_offset() as usize] = b_thin(to_b(4 * plug));
+            let b_thick = UHR_WIRING[4 * plug + 3] as usize;
+            wiring[b.get_offset() as usize] = a_thin(to_a(b_thick));
+        });
+
+        Cipher::try_from(wiring).unwrap()
+    }
+}
+
+impl<A: Alphabet> Encode<A> for Uhr<A> {
+    fn encode(&self, c: Character<A>) -> Character<A> {
+        self.cipher.encode(c)
+    }
+}
+
+impl<A: Alphabet> Decode<A> for Uhr<A> {
+    fn decode(&self, c: Character<A>) -> Character<A> {
+        self.cipher.decode(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Uhr, UHR_POSITIONS};
+    use crate::{
+        cipher::{Decode, Encode},
+        common::Character,
+        plugboard::{Plug, Plugboard, Plugs},
+    };
+
+    fn pairs(s: &str) -> Vec<(Character, Character)> {
+        s.split_whitespace()
+            .map(|p| {
+                let mut c = p.chars().map(|c| Character::try_from(c).unwrap());
+                (c.next().unwrap(), c.next().unwrap())
+            })
+            .collect()
+    }
+
+    const PAIRS: &str = "AV BS CG DL FU HZ IN KM OW RX";
+
+    fn alphabet() -> impl Iterator<Item = Character> {
+        ('A'..='Z').map(|c| Character::try_from(c).unwrap())
+    }
+
+    #[test]
+    fn dial_zero_is_plugboard() {
+        let uhr = Uhr::new(&pairs(PAIRS), 0).unwrap();
+        let plugs: Vec<Plug> = pairs(PAIRS)
+            .into_iter()
+            .map(|p| Plug::try_from(p).unwrap())
+            .collect();
+        let plugboard = Plugboard::try_from(Plugs::try_from(plugs).unwrap()).unwrap();
+
+        alphabet().for_each(|c| assert_eq!(uhr.encode(c), plugboard.encode(c)))
+    }
+
+    #[test]
+    fn not_reciprocal() {
+        let uhr = Uhr::new(&pairs(PAIRS), 1).unwrap();
+
+        assert!(alphabet().any(|c| uhr.encode(uhr.encode(c)) != c));
+    }
+
+    #[test]
+    fn every_dial_position() {
+        // Unplugged letters pass straight through at every position of the dial
+        let unplugged: Vec<Character> = "EJPQTY"
+            .chars()
+            .map(|c| Character::try_from(c).unwrap())
+            .collect();
+        (0..UHR_POSITIONS).for_each(|dial| {
+            let uhr = Uhr::new(&pairs(PAIRS), dial).unwrap();
+            alphabet().for_each(|c| assert_eq!(uhr.decode(uhr.encode(c)), c));
+            unplugged
+                .iter()
+                .for_each(|c| assert_eq!(uhr.encode(*c), *c));
+        })
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Uhr::new(&pairs("AV BS CG DL FU HZ IN KM OW"), 0).is_err());
+        assert!(Uhr::new(&pairs("AV BS CG DL FU HZ IN KM OW RA"), 0).is_err());
+        assert!(Uhr::new(&pairs(PAIRS), UHR_POSITIONS).is_err());
+        assert!(Uhr::new(&pairs(PAIRS), 0)
+            .unwrap()
+            .with_dial(UHR_POSITIONS)
+            .is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command},
+};
+
+/// Rotors I, II and III at `AAA` with reflector B, listed from the fast rotor
+const CONFIG: &str = "reflector: B\nrotors: [[III, A], [II, A], [I, A]]\n";
+
+/// A path in the temporary directory that no other test uses
+fn scratch(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("libenigma-{}-{name}", process::id()))
+}
+
+#[test]
+fn same_input_and_output() {
+    let config = scratch("same.yaml");
+    let message = scratch("same.txt");
+    fs::write(&config, CONFIG).unwrap();
+    fs::write(&message, "AAAAA").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_libenigma"))
+        .arg("-c")
+        .arg(&config)
+        .arg("-i")
+        .arg(&message)
+        .arg("-o")
+        .arg(&message)
+        .status()
+        .unwrap();
+
+    assert!(!status.success());
+    assert_eq!(fs::read_to_string(&message).unwrap(), "AAAAA");
+    fs::remove_file(config).unwrap();
+    fs::remove_file(message).unwrap();
+}
+
+#[test]
+fn stream() {
+    let config = scratch("stream.yaml");
+    let input = scratch("stream-in.txt");
+    let output = scratch("stream-out.txt");
+    fs::write(&config, CONFIG).unwrap();
+    fs::write(&input, "AAAAA").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_libenigma"))
+        .arg("-c")
+        .arg(&config)
+        .arg("-i")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(&output).unwrap(), "BDZGO");
+    [config, input, output]
+        .into_iter()
+        .for_each(|path| fs::remove_file(path).unwrap());
+}
+
+#[test]
+fn formatted_missing_input() {
+    let config = scratch("missing.yaml");
+    fs::write(&config, CONFIG).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_libenigma"))
+        .arg("-c")
+        .arg(&config)
+        .arg("-i")
+        .arg(scratch("missing.txt"))
+        .arg("--grouping")
+        .arg("army")
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(1));
+    fs::remove_file(config).unwrap();
+}